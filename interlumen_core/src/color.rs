@@ -165,6 +165,10 @@ impl Color {
         }
     }
 
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn pow(&self, pow: f32) -> Self {
         Self {
             r: self.r.powf(pow),
@@ -276,6 +280,7 @@ impl PartialEq for Color {
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
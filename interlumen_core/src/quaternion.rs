@@ -1,7 +1,74 @@
+use std::ops::Mul;
+
 use crate::vec::Vec3;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Quaternion {
     pub re: f32,
     pub im: Vec3,
 }
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quaternion {
+    pub const IDENTITY: Self = Self {
+        re: 1.0,
+        im: Vec3(0.0, 0.0, 0.0),
+    };
+
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let (sin, cos) = (angle * 0.5).sin_cos();
+        Self {
+            re: cos,
+            im: axis.norm() * sin,
+        }
+    }
+
+    // Angles in radians around x, then y, then z
+    pub fn from_euler(angles: Vec3) -> Self {
+        Self::from_axis_angle(Vec3(0.0, 0.0, 1.0), angles.2)
+            * Self::from_axis_angle(Vec3(0.0, 1.0, 0.0), angles.1)
+            * Self::from_axis_angle(Vec3(1.0, 0.0, 0.0), angles.0)
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Self {
+            re: self.re,
+            im: -self.im,
+        }
+    }
+
+    pub fn rotate(&self, v: Vec3) -> Vec3 {
+        let t = self.im.cross(v) * 2.0;
+        v + t * self.re + self.im.cross(t)
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self {
+            re: self.re * other.re - self.im * other.im,
+            im: other.im * self.re + self.im * other.re + self.im.cross(other.im),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_vectors() {
+        let q = Quaternion::from_euler(Vec3(0.0, std::f32::consts::FRAC_PI_2, 0.0));
+        let v = q.rotate(Vec3(1.0, 0.0, 0.0));
+        assert!((v - Vec3(0.0, 0.0, -1.0)).len() < 1e-5);
+        let back = q.conjugate().rotate(v);
+        assert!((back - Vec3(1.0, 0.0, 0.0)).len() < 1e-5);
+    }
+}
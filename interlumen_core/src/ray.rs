@@ -4,10 +4,20 @@ use crate::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub dir: Vec3,
+    // Fraction of the shutter interval the ray was sent at, used for motion blur
+    pub time: f32,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, dir: Vec3) -> Self {
-        Self { origin, dir }
+        Self {
+            origin,
+            dir,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(origin: Vec3, dir: Vec3, time: f32) -> Self {
+        Self { origin, dir, time }
     }
 }
@@ -0,0 +1,375 @@
+use std::f32::consts::PI;
+
+use interlumen_core::{Color, Vec3};
+
+use crate::{basis, sample_cosine, PBRColor};
+
+pub struct BsdfSample {
+    pub dir: Vec3,
+    // BSDF times cosine divided by the pdf
+    pub weight: Color,
+    // Zero for lobes that can't be reached by light sampling
+    pub pdf: f32,
+}
+
+fn lerp(a: Color, b: Color, t: f32) -> Color {
+    a * (1.0 - t) + b * t
+}
+
+fn schlick_weight(cos: f32) -> f32 {
+    (1.0 - cos).clamp(0.0, 1.0).powi(5)
+}
+
+fn fresnel_schlick(f0: Color, cos: f32) -> Color {
+    lerp(f0, Color::WHITE, schlick_weight(cos))
+}
+
+// Unpolarized Fresnel reflectance, `eta` is the ratio of the incident over transmitted index
+fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let rp = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (rs * rs + rp * rp) / 2.0
+}
+
+fn refract(wo: Vec3, h: Vec3, eta: f32) -> Option<Vec3> {
+    let cos_i = wo * h;
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((-wo * eta + h * (eta * cos_i - cos_t)).norm())
+}
+
+fn reflect(wo: Vec3, h: Vec3) -> Vec3 {
+    h * (2.0 * (wo * h)) - wo
+}
+
+// Anisotropic GGX in the local shading frame (z is the normal)
+#[derive(Debug, Clone, Copy)]
+struct Ggx {
+    ax: f32,
+    ay: f32,
+}
+
+impl Ggx {
+    fn d(&self, h: Vec3) -> f32 {
+        if h.2 <= 0.0 {
+            return 0.0;
+        }
+        let e = (h.0 / self.ax).powi(2) + (h.1 / self.ay).powi(2) + h.2 * h.2;
+        1.0 / (PI * self.ax * self.ay * e * e)
+    }
+
+    fn g1(&self, w: Vec3) -> f32 {
+        let z2 = w.2 * w.2;
+        if z2 <= 0.0 {
+            return 0.0;
+        }
+        let a2 = (self.ax * w.0).powi(2) + (self.ay * w.1).powi(2);
+        2.0 / (1.0 + (1.0 + a2 / z2).sqrt())
+    }
+
+    fn g(&self, wo: Vec3, wi: Vec3) -> f32 {
+        self.g1(wo) * self.g1(wi)
+    }
+
+    // Half vector distributed proportionally to D(h) * cos(h)
+    fn sample(&self, u1: f32, u2: f32) -> Vec3 {
+        let r = (u1 / (1.0 - u1).max(1e-6)).sqrt();
+        let phi = 2.0 * PI * u2;
+        Vec3(-self.ax * r * phi.cos(), -self.ay * r * phi.sin(), 1.0).norm()
+    }
+
+    fn pdf_reflect(&self, wo: Vec3, h: Vec3) -> f32 {
+        self.d(h) * h.2 / (4.0 * (wo * h).abs()).max(1e-6)
+    }
+}
+
+// Disney-style principled BSDF evaluated in a local shading frame
+pub struct Bsdf {
+    t: Vec3,
+    b: Vec3,
+    n: Vec3,
+    // Whether the ray arrives from outside of the object
+    entering: bool,
+    base: Color,
+    spec_color: Color,
+    sheen_color: Color,
+    roughness: f32,
+    diffuse_weight: f32,
+    transmission_weight: f32,
+    clearcoat: f32,
+    ior: f32,
+    spec: Ggx,
+    coat: Ggx,
+    // Lobe selection probabilities: diffuse, specular, clearcoat, transmission
+    probs: [f32; 4],
+}
+
+impl Bsdf {
+    pub fn new(color: &PBRColor, norm: Vec3, geom_norm: Vec3, tangent: Vec3, wo: Vec3) -> Self {
+        let entering = wo * geom_norm >= 0.0;
+        let (mut n, geom_norm) = if entering {
+            (norm, geom_norm)
+        } else {
+            (-norm, -geom_norm)
+        };
+        if wo * n <= 0.0 {
+            n = geom_norm;
+        }
+        let t = tangent - n * (n * tangent);
+        let (t, b) = if t.len() > 1e-4 {
+            let t = t.norm();
+            (t, n.cross(t))
+        } else {
+            basis(n)
+        };
+
+        let base = color.albedo;
+        let lum = base.luminance();
+        let tint = if lum > 0.0 { base / lum } else { Color::WHITE };
+        let spec_color = lerp(
+            lerp(Color::WHITE, tint, color.specular_tint) * (0.08 * color.specular),
+            base,
+            color.metallic,
+        );
+        let sheen_color = lerp(Color::WHITE, tint, color.sheen_tint) * color.sheen;
+
+        let roughness = color.roughness.clamp(0.0, 1.0);
+        let aspect = (1.0 - 0.9 * color.anisotropic.clamp(0.0, 1.0)).sqrt();
+        let alpha = roughness * roughness;
+        let spec = Ggx {
+            ax: (alpha / aspect).max(1e-3),
+            ay: (alpha * aspect).max(1e-3),
+        };
+        let coat_alpha = 0.1 * (1.0 - color.clearcoat_gloss) + 0.001 * color.clearcoat_gloss;
+        let coat = Ggx {
+            ax: coat_alpha,
+            ay: coat_alpha,
+        };
+
+        let metallic = color.metallic.clamp(0.0, 1.0);
+        let transmission = color.transmission.clamp(0.0, 1.0);
+        let diffuse_weight = (1.0 - metallic) * (1.0 - transmission);
+        let transmission_weight = (1.0 - metallic) * transmission;
+
+        let mut probs = [
+            diffuse_weight * lum.max(0.05),
+            (1.0 - transmission_weight) * spec_color.luminance().max(0.25),
+            0.25 * color.clearcoat,
+            transmission_weight,
+        ];
+        let total: f32 = probs.iter().sum();
+        for p in probs.iter_mut() {
+            *p /= total;
+        }
+
+        Self {
+            t,
+            b,
+            n,
+            entering,
+            base,
+            spec_color,
+            sheen_color,
+            roughness,
+            diffuse_weight,
+            transmission_weight,
+            clearcoat: color.clearcoat,
+            ior: color.ior,
+            spec,
+            coat,
+            probs,
+        }
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.n
+    }
+
+    fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3(v * self.t, v * self.b, v * self.n)
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        self.t * v.0 + self.b * v.1 + self.n * v.2
+    }
+
+    // Reflection lobes times the cosine term, transmission is only reachable by sampling
+    pub fn eval(&self, wo: Vec3, wi: Vec3) -> Color {
+        let (wo, wi) = (self.to_local(wo), self.to_local(wi));
+        if wo.2 <= 0.0 || wi.2 <= 0.0 {
+            return Color::BLACK;
+        }
+        let h = (wo + wi).norm();
+        let cos_d = wi * h;
+
+        let mut f = Color::BLACK;
+        if self.diffuse_weight > 0.0 {
+            let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+            let fd = (1.0 + (fd90 - 1.0) * schlick_weight(wi.2))
+                * (1.0 + (fd90 - 1.0) * schlick_weight(wo.2));
+            let sheen = self.sheen_color * schlick_weight(cos_d);
+            f += (self.base * (fd / PI) + sheen) * self.diffuse_weight;
+        }
+
+        let spec = self.spec.d(h) * self.spec.g(wo, wi) / (4.0 * wo.2 * wi.2);
+        f += fresnel_schlick(self.spec_color, cos_d) * spec * (1.0 - self.transmission_weight);
+
+        if self.clearcoat > 0.0 {
+            let fc = 0.04 + 0.96 * schlick_weight(cos_d);
+            let coat = self.coat.d(h) * self.coat.g(wo, wi) / (4.0 * wo.2 * wi.2);
+            f += Color::WHITE * (0.25 * self.clearcoat * fc * coat);
+        }
+
+        let mut f = f * wi.2;
+        f.a = 1.0;
+        f
+    }
+
+    pub fn pdf(&self, wo: Vec3, wi: Vec3) -> f32 {
+        let (wo, wi) = (self.to_local(wo), self.to_local(wi));
+        if wo.2 <= 0.0 || wi.2 <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).norm();
+        self.probs[0] * wi.2 / PI
+            + self.probs[1] * self.spec.pdf_reflect(wo, h)
+            + self.probs[2] * self.coat.pdf_reflect(wo, h)
+    }
+
+    pub fn sample(&self, wo_world: Vec3, u: [f32; 3]) -> Option<BsdfSample> {
+        let wo = self.to_local(wo_world);
+        if wo.2 <= 0.0 {
+            return None;
+        }
+
+        let (p_diffuse, p_spec, p_coat, _) = (self.probs[0], self.probs[1], self.probs[2], self.probs[3]);
+        let wi = if u[0] < p_diffuse {
+            sample_cosine(Vec3(0.0, 0.0, 1.0), u[1], u[2])
+        } else if u[0] < p_diffuse + p_spec {
+            reflect(wo, self.spec.sample(u[1], u[2]))
+        } else if u[0] < p_diffuse + p_spec + p_coat {
+            reflect(wo, self.coat.sample(u[1], u[2]))
+        } else {
+            return self.sample_transmission(wo, u);
+        };
+        if wi.2 <= 0.0 {
+            return None;
+        }
+
+        let dir = self.to_world(wi);
+        let pdf = self.pdf(wo_world, dir);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let mut weight = self.eval(wo_world, dir) / pdf;
+        weight.a = 1.0;
+        Some(BsdfSample { dir, weight, pdf })
+    }
+
+    // Rough dielectric lobe, picks reflection or refraction by the Fresnel term
+    fn sample_transmission(&self, wo: Vec3, u: [f32; 3]) -> Option<BsdfSample> {
+        let p_transmission = self.probs[3];
+        // Reuse the lobe selection number to choose between reflection and refraction
+        let u0 = ((u[0] - (1.0 - p_transmission)) / p_transmission).clamp(0.0, 1.0);
+        let ggx = Ggx {
+            ax: self.spec.ax.max(self.spec.ay),
+            ay: self.spec.ax.max(self.spec.ay),
+        };
+        let h = ggx.sample(u[1], u[2]);
+        let eta = if self.entering { 1.0 / self.ior } else { self.ior };
+        let cos_o = wo * h;
+        if cos_o <= 0.0 {
+            return None;
+        }
+        let fresnel = fresnel_dielectric(cos_o, eta);
+
+        let (wi, tint, refracted) = match refract(wo, h, eta) {
+            Some(wi) if u0 >= fresnel => (wi, self.base, true),
+            _ => (reflect(wo, h), Color::WHITE, false),
+        };
+        // Reflection below the surface or refraction above it
+        if (wi.2 > 0.0) == refracted {
+            return None;
+        }
+
+        let weight = ggx.g(wo, wi) * cos_o / (wo.2 * h.2).max(1e-6);
+        let mut color = tint * (weight * self.transmission_weight / p_transmission);
+        color.a = 1.0;
+        Some(BsdfSample {
+            dir: self.to_world(wi),
+            weight: color,
+            pdf: 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bsdf(color: &PBRColor) -> (Bsdf, Vec3) {
+        let n = Vec3(0.0, 1.0, 0.0);
+        let wo = Vec3(0.3, 0.8, 0.1).norm();
+        (Bsdf::new(color, n, n, Vec3(1.0, 0.0, 0.0), wo), wo)
+    }
+
+    // Average reflected energy from a white surface must not exceed one
+    fn albedo(color: &PBRColor) -> f32 {
+        let (bsdf, wo) = bsdf(color);
+        let n = 64;
+        let mut sum = 0.0;
+        for i in 0..n * n {
+            let u = [
+                ((i * 7919) % (n * n)) as f32 / (n * n) as f32,
+                ((i % n) as f32 + 0.5) / n as f32,
+                ((i / n) as f32 + 0.5) / n as f32,
+            ];
+            if let Some(sample) = bsdf.sample(wo, u) {
+                sum += sample.weight.g;
+            }
+        }
+        sum / (n * n) as f32
+    }
+
+    #[test]
+    fn energy_conserving() {
+        let mut color = PBRColor::new(Color::WHITE, Color::BLACK, 0.5);
+        assert!(albedo(&color) <= 1.05);
+        color.metallic = 1.0;
+        color.anisotropic = 0.8;
+        assert!(albedo(&color) <= 1.05);
+        color.metallic = 0.0;
+        color.clearcoat = 1.0;
+        color.sheen = 1.0;
+        assert!(albedo(&color) <= 1.1);
+    }
+
+    #[test]
+    fn sample_matches_eval() {
+        let mut color = PBRColor::new(Color::new(0.8, 0.4, 0.2, 1.0), Color::BLACK, 0.3);
+        color.clearcoat = 0.5;
+        let (bsdf, wo) = bsdf(&color);
+        let sample = bsdf.sample(wo, [0.7, 0.3, 0.6]).unwrap();
+        let expected = bsdf.eval(wo, sample.dir) / bsdf.pdf(wo, sample.dir);
+        assert!((sample.weight.r - expected.r).abs() < 1e-4);
+        assert!((sample.pdf - bsdf.pdf(wo, sample.dir)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn transmission_refracts() {
+        let mut color = PBRColor::new(Color::WHITE, Color::BLACK, 0.0);
+        color.transmission = 1.0;
+        let (bsdf, wo) = bsdf(&color);
+        let sample = bsdf.sample(wo, [0.99, 0.5, 0.5]).unwrap();
+        assert!(sample.dir.1 < 0.0);
+        assert_eq!(sample.pdf, 0.0);
+    }
+}
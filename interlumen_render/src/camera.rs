@@ -1,15 +1,34 @@
+use std::f32::consts::PI;
+
 use interlumen_core::{Ray, Vec3};
 
-#[derive(Debug, Clone)]
+use crate::Sampler;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     pub pos: Vec3,
     pub dir: Vec3,
     pub fov: f32,
+    // Lens diameter, 0 gives a pinhole camera with everything in focus
+    pub aperture: f32,
+    // Distance to the plane in perfect focus
+    pub focus_dist: f32,
+    // Change of `pos` and `dir` while the shutter is open
+    pub motion: Vec3,
+    pub dir_motion: Vec3,
 }
 
 impl Camera {
     pub fn new(pos: Vec3, dir: Vec3, fov: f32) -> Self {
-        Self { pos, dir, fov }
+        Self {
+            pos,
+            dir,
+            fov,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            motion: Vec3(0.0, 0.0, 0.0),
+            dir_motion: Vec3(0.0, 0.0, 0.0),
+        }
     }
 
     pub fn unit() -> Self {
@@ -17,9 +36,34 @@ impl Camera {
             pos: Vec3(0.0, 0.0, 0.0),
             dir: Vec3(0.0, 0.0, 1.0),
             fov: 90.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            motion: Vec3(0.0, 0.0, 0.0),
+            dir_motion: Vec3(0.0, 0.0, 0.0),
+        }
+    }
+
+    // Where the camera is `time` into the shutter interval, between 0 and 1
+    pub fn at_time(&self, time: f32) -> Self {
+        Self {
+            pos: self.pos + self.motion * time,
+            dir: self.dir + self.dir_motion * time,
+            ..self.clone()
         }
     }
 
+    // Right, up and forward axes of the view, `dir` is the forward axis
+    pub fn basis(&self) -> (Vec3, Vec3, Vec3) {
+        let forward = self.dir.norm();
+        let up = if forward.1.abs() > 0.999 {
+            Vec3(0.0, 0.0, 1.0)
+        } else {
+            Vec3(0.0, 1.0, 0.0)
+        };
+        let right = up.cross(forward).norm();
+        (right, forward.cross(right), forward)
+    }
+
     pub fn get_pixel_ray(
         &self,
         x: usize,
@@ -28,12 +72,60 @@ impl Camera {
         screen_h: usize,
         pixel_ratio: f32,
     ) -> Ray {
+        Ray::new(self.pos, self.direction(x as f32, y as f32, screen_w, screen_h, pixel_ratio))
+    }
+
+    // Ray jittered inside the pixel footprint and across the lens
+    pub fn sample_pixel_ray(
+        &self,
+        x: usize,
+        y: usize,
+        screen_w: usize,
+        screen_h: usize,
+        pixel_ratio: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Ray {
+        let (jx, jy) = sampler.next_2d();
+        let dir = self.direction(x as f32 + jx - 0.5, y as f32 + jy - 0.5, screen_w, screen_h, pixel_ratio);
+        if self.aperture <= 0.0 {
+            return Ray::new(self.pos, dir);
+        }
+        let (right, up, forward) = self.basis();
+        let (u1, u2) = sampler.next_2d();
+        let r = 0.5 * self.aperture * u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let origin = self.pos + right * (r * phi.cos()) + up * (r * phi.sin());
+        let focus = self.pos + dir * (self.focus_dist / (dir * forward));
+        Ray::new(origin, (focus - origin).norm())
+    }
+
+    fn direction(&self, x: f32, y: f32, screen_w: usize, screen_h: usize, pixel_ratio: f32) -> Vec3 {
         let cx = screen_w as f32 / 2.0;
         let cy = screen_h as f32 / 2.0;
         let ratio = screen_h as f32 / screen_w as f32;
         let hfov = (self.fov / 2.0).to_radians();
-        let w = (x as f32 - cx) / cx;
-        let h = -(y as f32 - cy) / cy * ratio * pixel_ratio;
-        Ray::new(self.pos, Vec3(w, h, 1.0 / hfov.tan()).norm())
+        let w = (x - cx) / cx;
+        let h = -(y - cy) / cy * ratio * pixel_ratio;
+        let local = Vec3(w, h, 1.0 / hfov.tan()).norm();
+        let (right, up, forward) = self.basis();
+        right * local.0 + up * local.1 + forward * local.2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn center_ray_follows_dir() {
+        let dir = Vec3(1.0, 0.5, -1.0).norm();
+        let camera = Camera::new(Vec3(0.0, 0.0, 0.0), dir, 90.0);
+        let ray = camera.get_pixel_ray(50, 50, 100, 100, 1.0);
+        assert!((ray.dir - dir).len() < 1e-5);
+
+        // Pixels to the right of the center point to the right of the camera
+        let (right, up, _) = camera.basis();
+        let ray = camera.get_pixel_ray(90, 10, 100, 100, 1.0);
+        assert!(ray.dir * right > 0.0 && ray.dir * up > 0.0);
     }
 }
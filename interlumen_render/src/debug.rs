@@ -0,0 +1,125 @@
+use interlumen_core::Color;
+
+use crate::{Material, Renderer, RendererSettings, Sampler, SceneData};
+
+// What `render_pixel` outputs. Everything but `Path` shows a single surface
+// property of the first hit and skips the light transport entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    #[default]
+    Path,
+    Normals,
+    Uv,
+    // False colour per material index, magenta for missing materials
+    Material,
+    Depth,
+    // Sphere-tracing iterations, blue for few and red for `max_iter`
+    Steps,
+    Albedo,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 7] = [
+        RenderMode::Path,
+        RenderMode::Normals,
+        RenderMode::Uv,
+        RenderMode::Material,
+        RenderMode::Depth,
+        RenderMode::Steps,
+        RenderMode::Albedo,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderMode::Path => "path",
+            RenderMode::Normals => "normals",
+            RenderMode::Uv => "uv",
+            RenderMode::Material => "material",
+            RenderMode::Depth => "depth",
+            RenderMode::Steps => "steps",
+            RenderMode::Albedo => "albedo",
+        }
+    }
+}
+
+fn hue(h: f32) -> Color {
+    let channel = |offset: f32| (((h + offset).fract() * 6.0 - 3.0).abs() - 1.0).clamp(0.0, 1.0);
+    Color::new(channel(0.0), channel(2.0 / 3.0), channel(1.0 / 3.0), 1.0)
+}
+
+fn heatmap(t: f32) -> Color {
+    hue((1.0 - t.clamp(0.0, 1.0)) * 2.0 / 3.0)
+}
+
+impl Renderer {
+    pub fn debug_pixel(
+        settings: &RendererSettings,
+        data: &SceneData,
+        x: usize,
+        y: usize,
+        screen_w: usize,
+        screen_h: usize,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let &SceneData { scene, materials, camera, .. } = data;
+        let ray = camera.sample_pixel_ray(x, y, screen_w, screen_h, settings.pixel_ratio, sampler);
+        let (hit, steps) = Renderer::march(settings, &ray, scene, 1.0);
+        let color = match (settings.render_mode, hit) {
+            (RenderMode::Steps, _) => heatmap(steps as f32 / settings.max_iter.max(1) as f32),
+            (_, None) => Color::BLACK,
+            (mode, Some(hit)) => {
+                let obj = hit.object;
+                let material = materials.get(obj.material());
+                match mode {
+                    RenderMode::Normals => {
+                        let n = obj.norm(hit.point) * 0.5 + 0.5;
+                        Color::new(n.0, n.1, n.2, 1.0)
+                    }
+                    RenderMode::Uv => {
+                        let uv = obj.uv(hit.point);
+                        Color::new(uv.0.rem_euclid(1.0), uv.1.rem_euclid(1.0), 0.0, 1.0)
+                    }
+                    RenderMode::Material => match material {
+                        // Golden ratio steps keep neighbouring indices apart
+                        Some(_) => hue(obj.material() as f32 * 0.618_034),
+                        None => Color::new(1.0, 0.0, 1.0, 1.0),
+                    },
+                    RenderMode::Depth => Color::new_value(1.0 / (1.0 + hit.distance * 0.25), 1.0),
+                    _ => {
+                        let uv = obj.uv(hit.point);
+                        match material {
+                            Some(material) => material.get_color(uv, hit.point).albedo,
+                            None => Renderer::FALLBACK_MATERIAL.get_color(uv, hit.point).albedo,
+                        }
+                    }
+                }
+            }
+        };
+        // Undo the display transform so the colours are shown as computed
+        Color { a: 1.0, ..color }.pow(1.0 / 2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RendererDriver, Sphere};
+    use interlumen_core::Vec3;
+
+    #[test]
+    fn debug_modes() {
+        let mut driver = RendererDriver::new();
+        driver.scene_mut().push(Box::new(Sphere::new(Vec3(0.0, 0.0, 3.0), 1.0, 4)));
+        let center = |driver: &mut RendererDriver, mode| {
+            driver.settings.render_mode = mode;
+            driver.draw_image(9, 9, 0)[4 * 9 + 4]
+        };
+        // The sphere faces the camera and has no material
+        let normal = center(&mut driver, RenderMode::Normals);
+        assert!(normal.b < 0.2 && (0.3..0.7).contains(&normal.r) && (0.3..0.7).contains(&normal.g));
+        assert_eq!(center(&mut driver, RenderMode::Material), Color::new(1.0, 0.0, 1.0, 1.0));
+        let depth = center(&mut driver, RenderMode::Depth);
+        assert!((depth.r - 1.0 / (1.0 + 2.0 * 0.25)).abs() < 0.01);
+        assert!(center(&mut driver, RenderMode::Steps).b > 0.5);
+    }
+}
@@ -0,0 +1,325 @@
+use std::f32::consts::PI;
+
+use interlumen_core::{Color, Vec3};
+
+use crate::{basis, Image, Sampler};
+
+pub enum Environment {
+    Color(Color),
+    Gradient { top: Color, bottom: Color },
+    Sky(Sky),
+    Map(EnvironmentMap),
+}
+
+impl Environment {
+    pub fn radiance(&self, dir: Vec3) -> Color {
+        match self {
+            Environment::Color(color) => *color,
+            Environment::Gradient { top, bottom } => {
+                let t = (dir.1 * 0.5 + 0.5).clamp(0.0, 1.0);
+                *bottom * (1.0 - t) + *top * t
+            }
+            Environment::Sky(sky) => sky.radiance(dir),
+            Environment::Map(map) => map.radiance(dir),
+        }
+    }
+
+    // Importance-sampled direction towards the environment with its radiance and pdf.
+    // Image maps and the sun disk are sampled explicitly, smooth skies are found by bounces.
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> Option<(Vec3, Color, f32)> {
+        match self {
+            Environment::Map(map) => {
+                let (u1, u2) = sampler.next_2d();
+                Some(map.sample(u1, u2))
+            }
+            Environment::Sky(sky) => {
+                let (u1, u2) = sampler.next_2d();
+                sky.sample_sun(u1, u2)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn pdf(&self, dir: Vec3) -> f32 {
+        match self {
+            Environment::Map(map) => map.pdf(dir),
+            Environment::Sky(sky) => sky.sun_pdf(dir),
+            _ => 0.0,
+        }
+    }
+}
+
+fn xyz_to_rgb(x: f32, y: f32, z: f32) -> Color {
+    Color::new(
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+        1.0,
+    )
+}
+
+// Preetham et al. "A Practical Analytic Model for Daylight"
+pub struct Sky {
+    pub sun_dir: Vec3,
+    pub turbidity: f32,
+    pub intensity: f32,
+    // Angular radius of the visible sun disk in degrees
+    pub sun_radius: f32,
+    pub sun_intensity: f32,
+    zenith: [f32; 3],
+    perez: [[f32; 5]; 3],
+}
+
+impl Sky {
+    pub fn new(sun_dir: Vec3, turbidity: f32, intensity: f32) -> Self {
+        let sun_dir = sun_dir.norm();
+        let t = turbidity;
+        let theta_s = sun_dir.1.clamp(0.0, 1.0).acos();
+        let (t2, th, th2, th3) = (t * t, theta_s, theta_s * theta_s, theta_s * theta_s * theta_s);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let zenith_x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_yc = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let mut sky = Self {
+            sun_dir,
+            turbidity,
+            intensity,
+            sun_radius: 0.27,
+            sun_intensity: 0.0,
+            zenith: [zenith_y, zenith_x, zenith_yc],
+            perez,
+        };
+        // Normalize each channel by its value at the zenith
+        for i in 0..3 {
+            let f0 = perez_fn(&sky.perez[i], 0.0, theta_s);
+            sky.zenith[i] /= f0;
+        }
+        sky
+    }
+
+    pub fn radiance(&self, dir: Vec3) -> Color {
+        let cos_theta = dir.1.max(0.01);
+        let cos_gamma = (dir * self.sun_dir).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let theta = cos_theta.acos();
+
+        let big_y = self.zenith[0] * perez_fn(&self.perez[0], theta, gamma);
+        let x = self.zenith[1] * perez_fn(&self.perez[1], theta, gamma);
+        let y = self.zenith[2] * perez_fn(&self.perez[2], theta, gamma);
+
+        let y = y.max(1e-4);
+        let color = xyz_to_rgb(x / y * big_y, big_y, (1.0 - x - y) / y * big_y);
+        let mut color = color.clamp(0.0, f32::MAX) * (self.intensity * 0.05);
+        if self.in_sun(dir) {
+            color += Color::new(1.0, 0.95, 0.9, 1.0) * self.sun_intensity;
+        }
+        color.a = 1.0;
+        color
+    }
+
+    fn in_sun(&self, dir: Vec3) -> bool {
+        let gamma = (dir * self.sun_dir).clamp(-1.0, 1.0).acos();
+        self.sun_intensity > 0.0 && dir.1 > 0.0 && gamma.to_degrees() < self.sun_radius
+    }
+
+    // Uniform over the cone of the sun disk
+    pub fn sun_pdf(&self, dir: Vec3) -> f32 {
+        if !self.in_sun(dir) {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - self.sun_radius.to_radians().cos()))
+    }
+
+    // The disk is tiny and bright, bounces alone would only find it as fireflies
+    pub fn sample_sun(&self, u1: f32, u2: f32) -> Option<(Vec3, Color, f32)> {
+        let cos_max = self.sun_radius.to_radians().cos();
+        let cos_theta = 1.0 - u1 * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        let (t, b) = basis(self.sun_dir);
+        let dir = (t * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin()) + self.sun_dir * cos_theta).norm();
+        let pdf = self.sun_pdf(dir);
+        (pdf > 0.0).then(|| (dir, self.radiance(dir), pdf))
+    }
+}
+
+fn perez_fn(c: &[f32; 5], theta: f32, gamma: f32) -> f32 {
+    let cos_gamma = gamma.cos();
+    (1.0 + c[0] * (c[1] / theta.cos().max(0.01)).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+}
+
+// Equirectangular image, importance sampled by luminance
+pub struct EnvironmentMap {
+    pub image: Image,
+    pub intensity: f32,
+    // Rotation around the vertical axis in degrees
+    pub rotation: f32,
+    marginal: Vec<f32>,
+    conditional: Vec<f32>,
+    total: f32,
+}
+
+// Returns the sampled bucket and the position of `u` inside it
+fn sample_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+    let target = u * cdf[cdf.len() - 1];
+    let i = cdf.partition_point(|&v| v <= target).min(cdf.len() - 1);
+    let start = if i > 0 { cdf[i - 1] } else { 0.0 };
+    let frac = ((target - start) / (cdf[i] - start)).clamp(0.0, 0.999);
+    (i, frac)
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Image, intensity: f32, rotation: f32) -> Self {
+        let (w, h) = (image.width, image.height);
+        let mut conditional = vec![0.0; w * h];
+        let mut marginal = vec![0.0; h];
+        let mut acc = 0.0;
+        for y in 0..h {
+            let sin_theta = ((y as f32 + 0.5) / h as f32 * PI).sin();
+            let mut row = 0.0;
+            for x in 0..w {
+                row += image.get(x, y).luminance() * sin_theta + 1e-6;
+                conditional[y * w + x] = row;
+            }
+            acc += row;
+            marginal[y] = acc;
+        }
+        Self {
+            image,
+            intensity,
+            rotation,
+            marginal,
+            conditional,
+            total: acc,
+        }
+    }
+
+    fn dir_to_uv(&self, dir: Vec3) -> (f32, f32) {
+        let phi = dir.2.atan2(dir.0) - self.rotation.to_radians();
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        let v = dir.1.clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn uv_to_dir(&self, u: f32, v: f32) -> Vec3 {
+        let phi = (u - 0.5) * 2.0 * PI + self.rotation.to_radians();
+        let theta = v * PI;
+        Vec3(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+    }
+
+    fn texel(&self, u: f32, v: f32) -> (usize, usize) {
+        let x = ((u * self.image.width as f32) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f32) as usize).min(self.image.height - 1);
+        (x, y)
+    }
+
+    pub fn radiance(&self, dir: Vec3) -> Color {
+        let (u, v) = self.dir_to_uv(dir);
+        let (x, y) = self.texel(u, v);
+        self.image.get(x, y) * self.intensity
+    }
+
+    pub fn pdf(&self, dir: Vec3) -> f32 {
+        let (u, v) = self.dir_to_uv(dir);
+        let (x, y) = self.texel(u, v);
+        let w = self.image.width;
+        let h = self.image.height;
+        let sin_theta = (1.0 - dir.1 * dir.1).max(0.0).sqrt();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let row_sin_theta = ((y as f32 + 0.5) / h as f32 * PI).sin();
+        let weight = self.image.get(x, y).luminance() * row_sin_theta + 1e-6;
+        weight / self.total * (w * h) as f32 / (2.0 * PI * PI * sin_theta)
+    }
+
+    pub fn sample(&self, u1: f32, u2: f32) -> (Vec3, Color, f32) {
+        let w = self.image.width;
+        let (y, fy) = sample_cdf(&self.marginal, u1);
+        let (x, fx) = sample_cdf(&self.conditional[y * w..(y + 1) * w], u2);
+        let u = (x as f32 + fx) / w as f32;
+        let v = (y as f32 + fy) / self.image.height as f32;
+        let dir = self.uv_to_dir(u, v);
+        (dir, self.image.get(x, y) * self.intensity, self.pdf(dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_pdf_integrates_to_one() {
+        let mut image = Image::new(16, 8);
+        image.pixels[3 * 16 + 5] = Color::new_value(50.0, 1.0);
+        image.pixels[6 * 16 + 12] = Color::new(0.0, 4.0, 1.0, 1.0);
+        let map = EnvironmentMap::new(image, 1.0, 30.0);
+
+        let n = 200;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..2 * n {
+                let theta = (i as f32 + 0.5) / n as f32 * PI;
+                let phi = (j as f32 + 0.5) / (2 * n) as f32 * 2.0 * PI;
+                let dir = Vec3(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                sum += map.pdf(dir) * theta.sin();
+            }
+        }
+        let integral = sum * (PI / n as f32) * (PI / n as f32);
+        assert!((integral - 1.0).abs() < 0.02, "{integral}");
+    }
+
+    #[test]
+    fn sky_samples_sun() {
+        let mut sky = Sky::new(Vec3(0.3, 0.5, 0.4), 3.0, 1.0);
+        sky.sun_intensity = 20.0;
+        let (dir, radiance, pdf) = sky.sample_sun(0.3, 0.7).unwrap();
+        assert!(dir * sky.sun_dir > 0.9999);
+        assert_eq!(pdf, sky.sun_pdf(dir));
+        assert!(radiance.g > 19.0);
+        assert_eq!(sky.sun_pdf(Vec3(0.0, 1.0, 0.0)), 0.0);
+        sky.sun_intensity = 0.0;
+        assert!(sky.sample_sun(0.3, 0.7).is_none());
+    }
+
+    #[test]
+    fn map_sample_matches_pdf() {
+        let mut image = Image::new(8, 4);
+        image.pixels[9] = Color::new_value(10.0, 1.0);
+        let map = EnvironmentMap::new(image, 2.0, 0.0);
+        let (dir, radiance, pdf) = map.sample(0.5, 0.5);
+        assert!((pdf - map.pdf(dir)).abs() < 1e-3);
+        assert_eq!(radiance, map.radiance(dir));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail};
+use interlumen_core::Color;
+
+#[derive(Debug, Clone, Default)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::BLACK; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => Image::load_png(path),
+            Some("ppm") => Image::load_ppm(path),
+            Some("hdr") => Image::load_hdr(path),
+            _ => bail!("Unknown image format {}", path.display()),
+        }
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+        if info.width == 0 || info.height == 0 {
+            bail!("Empty PNG image");
+        }
+
+        let channels = info.color_type.samples();
+        let mut image = Image::new(info.width as usize, info.height as usize);
+        for (pixel, chunk) in image.pixels.iter_mut().zip(data.chunks(channels)) {
+            let v = |i: usize| chunk[i] as f32 / 255.0;
+            *pixel = match channels {
+                1 => Color::new(v(0), v(0), v(0), 1.0),
+                2 => Color::new(v(0), v(0), v(0), v(1)),
+                3 => Color::new(v(0), v(1), v(2), 1.0),
+                _ => Color::new(v(0), v(1), v(2), v(3)),
+            };
+        }
+        Ok(image)
+    }
+
+    // 8-bit RGB bytes, the pixels have to be display ready already
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| {
+                let c = pixel.as_color32();
+                [c.r, c.g, c.b]
+            })
+            .collect()
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.to_rgb8())?;
+        Ok(())
+    }
+
+    pub fn load_ppm(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Image::read_ppm(&data)
+    }
+
+    // Parses binary (P6) and plain (P3) portable pixmaps
+    pub fn read_ppm(data: &[u8]) -> anyhow::Result<Self> {
+        let mut pos = 0;
+        let mut token = || -> anyhow::Result<String> {
+            loop {
+                while pos < data.len() && data[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+                if pos < data.len() && data[pos] == b'#' {
+                    while pos < data.len() && data[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                bail!("Unexpected end of PPM data");
+            }
+            Ok(String::from_utf8_lossy(&data[start..pos]).into_owned())
+        };
+
+        let magic = token()?;
+        let width: usize = token()?.parse()?;
+        let height: usize = token()?.parse()?;
+        let max: f32 = token()?.parse()?;
+        if width == 0 || height == 0 {
+            bail!("Empty PPM image");
+        }
+
+        let mut image = Image::new(width, height);
+        match magic.as_str() {
+            "P3" => {
+                for pixel in image.pixels.iter_mut() {
+                    let r: f32 = token()?.parse()?;
+                    let g: f32 = token()?.parse()?;
+                    let b: f32 = token()?.parse()?;
+                    *pixel = Color::new(r / max, g / max, b / max, 1.0);
+                }
+            }
+            "P6" => {
+                // A single whitespace byte separates the header from the raster
+                let start = pos + 1;
+                let wide = max > 255.0;
+                let size = if wide { 6 } else { 3 };
+                let raster = data
+                    .get(start..start + width * height * size)
+                    .ok_or_else(|| anyhow!("Truncated PPM data"))?;
+                for (pixel, chunk) in image.pixels.iter_mut().zip(raster.chunks(size)) {
+                    let v = |i: usize| {
+                        if wide {
+                            (chunk[i * 2] as f32 * 256.0 + chunk[i * 2 + 1] as f32) / max
+                        } else {
+                            chunk[i] as f32 / max
+                        }
+                    };
+                    *pixel = Color::new(v(0), v(1), v(2), 1.0);
+                }
+            }
+            _ => bail!("Unsupported PPM format {magic}"),
+        }
+        Ok(image)
+    }
+
+    // Loads a Radiance RGBE (.hdr) image with linear colors
+    pub fn load_hdr(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.starts_with("#?") {
+            bail!("Not a Radiance HDR file");
+        }
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                bail!("Unexpected end of HDR header");
+            }
+            let l = line.trim();
+            if l.is_empty() {
+                break;
+            }
+            if let Some(format) = l.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    bail!("Unsupported HDR format {format}");
+                }
+            }
+        }
+
+        line.clear();
+        reader.read_line(&mut line)?;
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (height, width) = match parts.as_slice() {
+            ["-Y", h, "+X", w] => (h.parse::<usize>()?, w.parse::<usize>()?),
+            _ => bail!("Unsupported HDR orientation {}", line.trim()),
+        };
+        if width == 0 || height == 0 {
+            bail!("Empty HDR image");
+        }
+
+        let mut image = Image::new(width, height);
+        let mut scanline = vec![[0u8; 4]; width];
+        for y in 0..height {
+            read_scanline(&mut reader, &mut scanline)?;
+            for (x, rgbe) in scanline.iter().enumerate() {
+                image.pixels[y * width + x] = rgbe_to_color(*rgbe);
+            }
+        }
+        Ok(image)
+    }
+}
+
+fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::BLACK;
+    }
+    let f = 2f32.powi(rgbe[3] as i32 - 136);
+    Color::new(rgbe[0] as f32 * f, rgbe[1] as f32 * f, rgbe[2] as f32 * f, 1.0)
+}
+
+fn read_scanline(reader: &mut impl Read, scanline: &mut [[u8; 4]]) -> anyhow::Result<()> {
+    let width = scanline.len();
+    let mut head = [0u8; 4];
+    reader.read_exact(&mut head)?;
+
+    // Flat (non run-length encoded) scanline
+    if !(8..=0x7fff).contains(&width) || head[0] != 2 || head[1] != 2 || head[2] & 0x80 != 0 {
+        scanline[0] = head;
+        for pixel in scanline.iter_mut().skip(1) {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+
+    if ((head[2] as usize) << 8 | head[3] as usize) != width {
+        return Err(anyhow!("Wrong HDR scanline width"));
+    }
+
+    // Adaptive RLE, each channel is stored separately
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            reader.read_exact(&mut count)?;
+            let count = count[0] as usize;
+            if count > 128 {
+                let run = count - 128;
+                if x + run > width {
+                    bail!("Bad HDR scanline data");
+                }
+                let mut value = [0u8; 1];
+                reader.read_exact(&mut value)?;
+                for pixel in &mut scanline[x..x + run] {
+                    pixel[channel] = value[0];
+                }
+                x += run;
+            } else {
+                if count == 0 || x + count > width {
+                    bail!("Bad HDR scanline data");
+                }
+                let mut values = vec![0u8; count];
+                reader.read_exact(&mut values)?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = value;
+                }
+                x += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_round_trip() {
+        let mut image = Image::new(2, 1);
+        image.pixels[1] = Color::new(1.0, 0.0, 1.0, 1.0);
+        let path = std::env::temp_dir().join(format!("interlumen_round_trip_{}.png", std::process::id()));
+        image.save_png(&path).unwrap();
+        let loaded = Image::load_png(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((loaded.width, loaded.height), (2, 1));
+        assert_eq!(loaded.to_rgb8(), image.to_rgb8());
+    }
+
+    #[test]
+    fn reject_empty_ppm() {
+        assert!(Image::read_ppm(b"P3 0 0 255").is_err());
+        assert!(Image::read_ppm(b"P3 1 1 255 255 0 0").is_ok());
+    }
+
+    #[test]
+    fn reject_empty_hdr() {
+        let path = std::env::temp_dir().join(format!("interlumen_empty_{}.hdr", std::process::id()));
+        std::fs::write(&path, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 0 +X 4\n").unwrap();
+        let result = Image::load_hdr(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
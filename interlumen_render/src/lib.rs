@@ -1,13 +1,35 @@
+mod bsdf;
 mod camera;
+mod debug;
+mod environment;
+mod image;
+mod light;
 mod material;
+mod medium;
 mod objects;
+mod procedural;
 mod renderer;
 mod renderer_driver;
+mod rng;
+mod sampler;
+mod texture;
+mod tiles;
 
+pub use bsdf::*;
 pub use camera::*;
+pub use debug::*;
+pub use environment::*;
+pub use image::*;
+pub use light::*;
 pub use material::*;
+pub use medium::*;
 pub use objects::*;
+pub use procedural::*;
 pub use renderer::*;
 pub use renderer_driver::*;
+pub use rng::*;
+pub use sampler::*;
+pub use texture::*;
+pub use tiles::*;
 
 pub type Scene = Vec<Box<dyn Object>>;
@@ -0,0 +1,234 @@
+use std::f32::consts::PI;
+
+use interlumen_core::{Color, Vec3};
+
+use crate::Sampler;
+
+pub struct LightSample {
+    // Normalized direction from the shaded point towards the light
+    pub dir: Vec3,
+    // Distance to the sampled point on the light, used for shadow rays
+    pub dist: f32,
+    // Irradiance arriving at the shaded point (before the cosine term)
+    pub radiance: Color,
+}
+
+// Points closer than this to a light get no light from it instead of a division by zero
+const MIN_DIST: f32 = 1e-4;
+
+pub trait Light: Send + Sync {
+    fn sample(&self, point: Vec3, sampler: &mut dyn Sampler) -> Option<LightSample>;
+}
+
+// Builds an orthonormal basis around `n`
+pub fn basis(n: Vec3) -> (Vec3, Vec3) {
+    let up = if n.1.abs() < 0.999 {
+        Vec3(0.0, 1.0, 0.0)
+    } else {
+        Vec3(1.0, 0.0, 0.0)
+    };
+    let t = up.cross(n).norm();
+    let b = n.cross(t);
+    (t, b)
+}
+
+// Cosine-weighted direction in the hemisphere around `n`
+pub fn sample_cosine(n: Vec3, u1: f32, u2: f32) -> Vec3 {
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = basis(n);
+    (t * (r * phi.cos()) + b * (r * phi.sin()) + n * (1.0 - u1).max(0.0).sqrt()).norm()
+}
+
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    pub pos: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Vec3, _sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let to_light = self.pos - point;
+        let dist = to_light.len();
+        if dist < MIN_DIST {
+            return None;
+        }
+        Some(LightSample {
+            dir: to_light / dist,
+            dist,
+            radiance: self.color * (self.intensity / (dist * dist)),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub pos: Vec3,
+    pub dir: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    // Full-intensity cone half-angle in degrees
+    pub inner_angle: f32,
+    // Cutoff cone half-angle in degrees
+    pub outer_angle: f32,
+    // Exponent applied to the transition between inner and outer cones
+    pub falloff: f32,
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Vec3, _sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let to_light = self.pos - point;
+        let dist = to_light.len();
+        if dist < MIN_DIST {
+            return None;
+        }
+        let dir = to_light / dist;
+
+        let cos_theta = -(dir * self.dir.norm());
+        let cos_outer = self.outer_angle.to_radians().cos();
+        let cos_inner = self.inner_angle.to_radians().cos();
+        if cos_theta <= cos_outer {
+            return None;
+        }
+        let t = ((cos_theta - cos_outer) / (cos_inner - cos_outer).max(1e-4)).clamp(0.0, 1.0);
+        let cone = (t * t * (3.0 - 2.0 * t)).powf(self.falloff);
+
+        Some(LightSample {
+            dir,
+            dist,
+            radiance: self.color * (self.intensity * cone / (dist * dist)),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    // Direction the light travels in
+    pub dir: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    // Angular radius of the light disk in degrees, 0 for hard shadows
+    pub angular_radius: f32,
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Vec3, sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let axis = -self.dir.norm();
+        let dir = if self.angular_radius > 0.0 {
+            let cos_max = self.angular_radius.to_radians().cos();
+            let (r1, r2) = sampler.next_2d();
+            let cos_theta = 1.0 - r1 * (1.0 - cos_max);
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let phi = 2.0 * PI * r2;
+            let (t, b) = basis(axis);
+            (t * (phi.cos() * sin_theta) + b * (phi.sin() * sin_theta) + axis * cos_theta).norm()
+        } else {
+            axis
+        };
+        Some(LightSample {
+            dir,
+            dist: f32::MAX,
+            radiance: self.color * self.intensity,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AreaLight {
+    // Center of the rectangle
+    pub pos: Vec3,
+    // Half-extent edge vectors, the light emits along `u x v`
+    pub u: Vec3,
+    pub v: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light for AreaLight {
+    fn sample(&self, point: Vec3, sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let (r1, r2) = sampler.next_2d();
+        let target = self.pos + self.u * (2.0 * r1 - 1.0) + self.v * (2.0 * r2 - 1.0);
+        let to_light = target - point;
+        let dist = to_light.len();
+        if dist < MIN_DIST {
+            return None;
+        }
+        let dir = to_light / dist;
+
+        let cross = self.u.cross(self.v);
+        let area = 4.0 * cross.len();
+        let cos_light = -(dir * cross.norm());
+        if cos_light <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            dir,
+            dist,
+            radiance: self.color * (self.intensity * cos_light * area / (dist * dist)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SamplerKind;
+
+    fn sample(light: &dyn Light, point: Vec3) -> Option<LightSample> {
+        light.sample(point, SamplerKind::Independent.create(0, 0, 0, 0).as_mut())
+    }
+
+    #[test]
+    fn point_light_falloff() {
+        let light = PointLight {
+            pos: Vec3(0.0, 2.0, 0.0),
+            color: Color::WHITE,
+            intensity: 8.0,
+        };
+        let s = sample(&light, Vec3(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!((s.dir.1, s.dist, s.radiance.r), (1.0, 2.0, 2.0));
+        assert_eq!(sample(&light, Vec3(0.0, 1.0, 0.0)).unwrap().radiance.r, 8.0);
+        // No NaN at the light itself
+        assert!(sample(&light, light.pos).is_none());
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = SpotLight {
+            pos: Vec3(0.0, 1.0, 0.0),
+            dir: Vec3(0.0, -1.0, 0.0),
+            color: Color::WHITE,
+            intensity: 1.0,
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+            falloff: 1.0,
+        };
+        assert_eq!(sample(&light, Vec3(0.0, 0.0, 0.0)).unwrap().radiance.r, 1.0);
+        // tan(25 degrees) is about 0.47, between the cones
+        let edge = sample(&light, Vec3(0.47, 0.0, 0.0)).unwrap().radiance.r;
+        assert!(edge > 0.0 && edge < 1.0 / (1.0 + 0.47 * 0.47));
+        assert!(sample(&light, Vec3(1.0, 0.0, 0.0)).is_none());
+        assert!(sample(&light, Vec3(0.0, 2.0, 0.0)).is_none());
+        assert!(sample(&light, light.pos).is_none());
+    }
+
+    #[test]
+    fn area_light_radiance() {
+        // 1x1 square facing down, u x v points down
+        let light = AreaLight {
+            pos: Vec3(0.0, 10.0, 0.0),
+            u: Vec3(0.5, 0.0, 0.0),
+            v: Vec3(0.0, 0.0, 0.5),
+            color: Color::WHITE,
+            intensity: 100.0,
+        };
+        // Far away the light acts like a point light scaled by its area
+        let s = sample(&light, Vec3(0.0, 0.0, 0.0)).unwrap();
+        assert!((s.radiance.r - 1.0).abs() < 0.02);
+        assert!(s.dir.1 > 0.99 && (s.dist - 10.0).abs() < 0.1);
+        // Nothing is emitted from the back side
+        assert!(sample(&light, Vec3(0.0, 20.0, 0.0)).is_none());
+    }
+}
@@ -1,13 +1,128 @@
 use interlumen_core::{Color, Vec3};
 
+use crate::{basis, ConstantTexture, Medium, Subsurface, Texture};
+
 pub struct PBRColor {
     pub albedo: Color,
     pub emit: Color,
     pub roughness: f32,
+    // Tangent-space shading normal, (0, 0, 1) keeps the geometric normal
+    pub normal: Vec3,
+    pub metallic: f32,
+    // Dielectric reflectance, 0.5 corresponds to 4% at normal incidence
+    pub specular: f32,
+    pub specular_tint: f32,
+    // Stretches highlights along the surface tangent
+    pub anisotropic: f32,
+    pub sheen: f32,
+    pub sheen_tint: f32,
+    pub clearcoat: f32,
+    pub clearcoat_gloss: f32,
+    pub transmission: f32,
+    pub ior: f32,
 }
 
-pub trait Material: Sync {
-    fn get_color(&self, uv: Vec3) -> PBRColor;
+impl PBRColor {
+    pub const FLAT_NORMAL: Vec3 = Vec3(0.0, 0.0, 1.0);
+
+    // Plain dielectric surface
+    pub fn new(albedo: Color, emit: Color, roughness: f32) -> Self {
+        Self {
+            albedo,
+            emit,
+            roughness,
+            normal: PBRColor::FLAT_NORMAL,
+            metallic: 0.0,
+            specular: 0.5,
+            specular_tint: 0.0,
+            anisotropic: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+        }
+    }
+
+    pub fn shading_normal(&self, norm: Vec3, tangent: Vec3) -> Vec3 {
+        if self.normal.0 == 0.0 && self.normal.1 == 0.0 {
+            return norm;
+        }
+        let t = tangent - norm * (norm * tangent);
+        let (t, b) = if t.len() > 1e-4 {
+            let t = t.norm();
+            (t, norm.cross(t))
+        } else {
+            basis(norm)
+        };
+        (t * self.normal.0 + b * self.normal.1 + norm * self.normal.2).norm()
+    }
+}
+
+pub trait Material: Send + Sync {
+    fn get_color(&self, uv: Vec3, point: Vec3) -> PBRColor;
+    // Objects with a medium are rendered as volumes instead of surfaces
+    fn medium(&self) -> Option<&Medium> {
+        None
+    }
+    // Light entering objects with subsurface scattering takes a random walk inside
+    fn subsurface(&self) -> Option<&Subsurface> {
+        None
+    }
+    // Named scalar parameters that can be tweaked while rendering
+    fn params(&self) -> Vec<Param<f32>> {
+        Vec::new()
+    }
+    // Same parameters in the same order, for changing them through `Param::set`
+    fn params_mut(&mut self) -> Vec<Param<&mut f32>> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Param<T> {
+    pub name: &'static str,
+    pub value: T,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Param<&mut f32> {
+    pub fn set(&mut self, value: f32) {
+        *self.value = value.clamp(self.min, self.max);
+    }
+}
+
+fn param<T>(name: &'static str, value: T, min: f32, max: f32) -> Param<T> {
+    Param { name, value, min, max }
+}
+
+fn color_params<T>(name: [&'static str; 3], [r, g, b]: [T; 3], max: f32) -> [Param<T>; 3] {
+    [param(name[0], r, 0.0, max), param(name[1], g, 0.0, max), param(name[2], b, 0.0, max)]
+}
+
+fn rgb(color: &Color) -> [f32; 3] {
+    [color.r, color.g, color.b]
+}
+
+fn rgb_mut(color: &mut Color) -> [&mut f32; 3] {
+    [&mut color.r, &mut color.g, &mut color.b]
+}
+
+// Shared by the basic and textured materials, emission can be brighter than white
+fn surface_params<T>(roughness: T, albedo: [T; 3], emit: [T; 3]) -> Vec<Param<T>> {
+    let mut params = vec![param("roughness", roughness, 0.0, 1.0)];
+    params.extend(color_params(["albedo.r", "albedo.g", "albedo.b"], albedo, 1.0));
+    params.extend(color_params(["emit.r", "emit.g", "emit.b"], emit, f32::INFINITY));
+    params
+}
+
+fn checker_params<T>(albedo1: [T; 3], albedo2: [T; 3]) -> Vec<Param<T>> {
+    let mut params = Vec::new();
+    params.extend(color_params(["albedo1.r", "albedo1.g", "albedo1.b"], albedo1, 1.0));
+    params.extend(color_params(["albedo2.r", "albedo2.g", "albedo2.b"], albedo2, 1.0));
+    params
 }
 
 #[derive(Debug, Clone)]
@@ -18,12 +133,16 @@ pub struct BasicMaterial {
 }
 
 impl Material for BasicMaterial {
-    fn get_color(&self, _uv: Vec3) -> PBRColor {
-        PBRColor {
-            albedo: self.albedo,
-            emit: self.emit,
-            roughness: self.roughness,
-        }
+    fn get_color(&self, _uv: Vec3, _point: Vec3) -> PBRColor {
+        PBRColor::new(self.albedo, self.emit, self.roughness)
+    }
+
+    fn params(&self) -> Vec<Param<f32>> {
+        surface_params(self.roughness, rgb(&self.albedo), rgb(&self.emit))
+    }
+
+    fn params_mut(&mut self) -> Vec<Param<&mut f32>> {
+        surface_params(&mut self.roughness, rgb_mut(&mut self.albedo), rgb_mut(&mut self.emit))
     }
 }
 
@@ -34,15 +153,183 @@ pub struct CheckerMaterial {
 }
 
 impl Material for CheckerMaterial {
-    fn get_color(&self, uv: Vec3) -> PBRColor {
-        PBRColor {
-            albedo: if (uv.0.ceil() + uv.1.ceil()) % 2.0 == 0.0 {
-                    self.albedo1
-                } else {
-                    self.albedo2
-                },
+    fn get_color(&self, uv: Vec3, _point: Vec3) -> PBRColor {
+        let albedo = if (uv.0.ceil() + uv.1.ceil()) % 2.0 == 0.0 {
+            self.albedo1
+        } else {
+            self.albedo2
+        };
+        PBRColor::new(albedo, Color::BLACK, 0.7)
+    }
+
+    fn params(&self) -> Vec<Param<f32>> {
+        checker_params(rgb(&self.albedo1), rgb(&self.albedo2))
+    }
+
+    fn params_mut(&mut self) -> Vec<Param<&mut f32>> {
+        checker_params(rgb_mut(&mut self.albedo1), rgb_mut(&mut self.albedo2))
+    }
+}
+
+// Constant parameters multiplied by optional texture maps
+pub struct TexturedMaterial {
+    pub albedo: Color,
+    pub emit: Color,
+    pub roughness: f32,
+    pub albedo_map: Option<Box<dyn Texture>>,
+    pub emit_map: Option<Box<dyn Texture>>,
+    pub roughness_map: Option<Box<dyn Texture>>,
+    // Tangent-space normal map encoded in [0, 1]
+    pub normal_map: Option<Box<dyn Texture>>,
+}
+
+impl TexturedMaterial {
+    pub fn new(albedo: Color) -> Self {
+        Self {
+            albedo,
             emit: Color::BLACK,
-            roughness: 0.7,
+            roughness: 1.0,
+            albedo_map: None,
+            emit_map: None,
+            roughness_map: None,
+            normal_map: None,
+        }
+    }
+}
+
+impl Material for TexturedMaterial {
+    fn get_color(&self, uv: Vec3, point: Vec3) -> PBRColor {
+        let map = |tex: &Option<Box<dyn Texture>>| tex.as_ref().map(|t| t.sample(uv, point));
+        let normal = match map(&self.normal_map) {
+            Some(n) => Vec3(n.r * 2.0 - 1.0, n.g * 2.0 - 1.0, n.b * 2.0 - 1.0).norm(),
+            None => PBRColor::FLAT_NORMAL,
+        };
+        PBRColor {
+            normal,
+            ..PBRColor::new(
+                map(&self.albedo_map).map_or(self.albedo, |c| self.albedo * c),
+                map(&self.emit_map).map_or(self.emit, |c| self.emit * c),
+                map(&self.roughness_map).map_or(self.roughness, |c| self.roughness * c.r),
+            )
+        }
+    }
+
+    fn params(&self) -> Vec<Param<f32>> {
+        surface_params(self.roughness, rgb(&self.albedo), rgb(&self.emit))
+    }
+
+    fn params_mut(&mut self) -> Vec<Param<&mut f32>> {
+        surface_params(&mut self.roughness, rgb_mut(&mut self.albedo), rgb_mut(&mut self.emit))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VolumeMaterial {
+    pub medium: Medium,
+}
+
+impl Material for VolumeMaterial {
+    fn get_color(&self, _uv: Vec3, _point: Vec3) -> PBRColor {
+        PBRColor::new(self.medium.albedo(), Color::BLACK, 1.0)
+    }
+
+    fn medium(&self) -> Option<&Medium> {
+        Some(&self.medium)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SubsurfaceMaterial {
+    pub subsurface: Subsurface,
+}
+
+impl Material for SubsurfaceMaterial {
+    fn get_color(&self, _uv: Vec3, _point: Vec3) -> PBRColor {
+        PBRColor::new(self.subsurface.albedo, Color::BLACK, 1.0)
+    }
+
+    fn subsurface(&self) -> Option<&Subsurface> {
+        Some(&self.subsurface)
+    }
+}
+
+// Layered material in the spirit of the Disney principled BSDF, every parameter is
+// a texture so constants, image maps and procedural patterns can be mixed freely.
+// Scalar parameters are read from the red channel.
+pub struct PrincipledMaterial {
+    pub base_color: Box<dyn Texture>,
+    pub emit: Box<dyn Texture>,
+    pub roughness: Box<dyn Texture>,
+    pub metallic: Box<dyn Texture>,
+    pub specular: Box<dyn Texture>,
+    pub specular_tint: Box<dyn Texture>,
+    pub anisotropic: Box<dyn Texture>,
+    pub sheen: Box<dyn Texture>,
+    pub sheen_tint: Box<dyn Texture>,
+    pub clearcoat: Box<dyn Texture>,
+    pub clearcoat_gloss: Box<dyn Texture>,
+    pub transmission: Box<dyn Texture>,
+    pub ior: f32,
+    // Tangent-space normal map encoded in [0, 1]
+    pub normal_map: Option<Box<dyn Texture>>,
+}
+
+impl PrincipledMaterial {
+    pub fn new(base_color: Color) -> Self {
+        let value = |v: f32| -> Box<dyn Texture> { Box::new(ConstantTexture(Color::new_value(v, 1.0))) };
+        Self {
+            base_color: Box::new(ConstantTexture(base_color)),
+            emit: Box::new(ConstantTexture(Color::BLACK)),
+            roughness: value(0.5),
+            metallic: value(0.0),
+            specular: value(0.5),
+            specular_tint: value(0.0),
+            anisotropic: value(0.0),
+            sheen: value(0.0),
+            sheen_tint: value(0.5),
+            clearcoat: value(0.0),
+            clearcoat_gloss: value(1.0),
+            transmission: value(0.0),
+            ior: 1.5,
+            normal_map: None,
         }
     }
 }
+
+impl Material for PrincipledMaterial {
+    fn get_color(&self, uv: Vec3, point: Vec3) -> PBRColor {
+        let value = |tex: &dyn Texture| tex.sample(uv, point).r;
+        let normal = match &self.normal_map {
+            Some(map) => {
+                let n = map.sample(uv, point);
+                Vec3(n.r * 2.0 - 1.0, n.g * 2.0 - 1.0, n.b * 2.0 - 1.0).norm()
+            }
+            None => PBRColor::FLAT_NORMAL,
+        };
+        PBRColor {
+            albedo: self.base_color.sample(uv, point),
+            emit: self.emit.sample(uv, point),
+            roughness: value(self.roughness.as_ref()),
+            normal,
+            metallic: value(self.metallic.as_ref()),
+            specular: value(self.specular.as_ref()),
+            specular_tint: value(self.specular_tint.as_ref()),
+            anisotropic: value(self.anisotropic.as_ref()),
+            sheen: value(self.sheen.as_ref()),
+            sheen_tint: value(self.sheen_tint.as_ref()),
+            clearcoat: value(self.clearcoat.as_ref()),
+            clearcoat_gloss: value(self.clearcoat_gloss.as_ref()),
+            transmission: value(self.transmission.as_ref()),
+            ior: self.ior,
+        }
+    }
+
+    // The other parameters are textures. Below 1 refraction stops making sense.
+    fn params(&self) -> Vec<Param<f32>> {
+        vec![param("ior", self.ior, 1.0, 3.0)]
+    }
+
+    fn params_mut(&mut self) -> Vec<Param<&mut f32>> {
+        vec![param("ior", &mut self.ior, 1.0, 3.0)]
+    }
+}
@@ -0,0 +1,193 @@
+use std::f32::consts::PI;
+
+use interlumen_core::{Color, Ray, Vec3};
+
+use crate::basis;
+
+pub fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
+
+// Samples a scattered direction around the propagation direction `dir`
+pub fn sample_henyey_greenstein(dir: Vec3, g: f32, u1: f32, u2: f32) -> Vec3 {
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * u1
+    } else {
+        let sq = (1.0 - g * g) / (1.0 + g - 2.0 * g * u1);
+        (1.0 + g * g - sq * sq) / (2.0 * g)
+    };
+    let cos_theta = cos_theta.clamp(-1.0, 1.0);
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = basis(dir);
+    (t * (phi.cos() * sin_theta) + b * (phi.sin() * sin_theta) + dir * cos_theta).norm()
+}
+
+// Homogeneous medium filling the inside of an object
+#[derive(Debug, Clone)]
+pub struct Medium {
+    pub absorption: f32,
+    pub scattering: f32,
+    // Tint of the scattered light
+    pub color: Color,
+    // Henyey-Greenstein asymmetry, positive values scatter forward
+    pub g: f32,
+}
+
+impl Medium {
+    pub fn extinction(&self) -> f32 {
+        self.absorption + self.scattering
+    }
+
+    pub fn albedo(&self) -> Color {
+        self.color * (self.scattering / self.extinction().max(1e-6))
+    }
+
+    pub fn sample_distance(&self, u: f32) -> Option<f32> {
+        let sigma_t = self.extinction();
+        if sigma_t <= 0.0 {
+            return None;
+        }
+        Some(-(1.0 - u).ln() / sigma_t)
+    }
+}
+
+// Dense medium for random-walk subsurface scattering, parameters are per color channel
+#[derive(Debug, Clone)]
+pub struct Subsurface {
+    // Single-scattering albedo
+    pub albedo: Color,
+    // Average distance travelled between scattering events
+    pub mean_free_path: Color,
+    pub g: f32,
+    // Maximum number of scattering events before the walk is terminated
+    pub max_steps: usize,
+}
+
+impl Subsurface {
+    pub fn extinction(&self) -> [f32; 3] {
+        let mfp = self.mean_free_path;
+        [1.0 / mfp.r.max(1e-4), 1.0 / mfp.g.max(1e-4), 1.0 / mfp.b.max(1e-4)]
+    }
+
+    // Picks a channel uniformly and samples a distance with its extinction
+    pub fn sample_distance(&self, u1: f32, u2: f32) -> f32 {
+        let sigma = self.extinction();
+        let channel = ((u1 * 3.0) as usize).min(2);
+        -(1.0 - u2).ln() / sigma[channel]
+    }
+
+    // Throughput weight for a walk segment of length `t`, `scattered` tells
+    // whether it ended in a scattering event or left the object
+    pub fn segment_weight(&self, t: f32, scattered: bool) -> Color {
+        let sigma = self.extinction();
+        let tr = sigma.map(|s| (-s * t).exp());
+        let albedo = [self.albedo.r, self.albedo.g, self.albedo.b];
+        let (weight, pdf) = if scattered {
+            let weight = [0, 1, 2].map(|c| sigma[c] * albedo[c] * tr[c]);
+            (weight, [0, 1, 2].map(|c| sigma[c] * tr[c]).iter().sum::<f32>() / 3.0)
+        } else {
+            (tr, tr.iter().sum::<f32>() / 3.0)
+        };
+        if pdf <= 0.0 {
+            return Color::BLACK;
+        }
+        Color::new(weight[0] / pdf, weight[1] / pdf, weight[2] / pdf, 1.0)
+    }
+}
+
+// Global fog whose density falls off exponentially above `height`
+#[derive(Debug, Clone)]
+pub struct HeightFog {
+    pub density: f32,
+    pub falloff: f32,
+    pub height: f32,
+    pub albedo: Color,
+    pub g: f32,
+}
+
+impl HeightFog {
+    // Density at the ray origin and its change rate along the ray
+    fn coefficients(&self, ray: &Ray) -> (f32, f32) {
+        let base = self.density * (-self.falloff * (ray.origin.1 - self.height)).exp();
+        (base, self.falloff * ray.dir.1)
+    }
+
+    pub fn optical_depth(&self, ray: &Ray, dist: f32) -> f32 {
+        let (base, k) = self.coefficients(ray);
+        if k.abs() < 1e-5 {
+            base * dist
+        } else {
+            base * (1.0 - (-k * dist).exp()) / k
+        }
+    }
+
+    pub fn transmittance(&self, ray: &Ray, dist: f32) -> f32 {
+        (-self.optical_depth(ray, dist)).exp()
+    }
+
+    // Inverts the optical depth to find the free-flight distance,
+    // `None` when the ray escapes the fog without scattering
+    pub fn sample_distance(&self, ray: &Ray, u: f32) -> Option<f32> {
+        let (base, k) = self.coefficients(ray);
+        if base <= 0.0 {
+            return None;
+        }
+        let target = -(1.0 - u).ln();
+        if k.abs() < 1e-5 {
+            return Some(target / base);
+        }
+        let arg = 1.0 - target * k / base;
+        if arg <= 0.0 {
+            return None;
+        }
+        Some(-arg.ln() / k)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hg_normalized() {
+        for g in [-0.5, 0.0, 0.3, 0.8] {
+            let n = 2000;
+            let integral: f32 = (0..n)
+                .map(|i| {
+                    let cos = -1.0 + 2.0 * (i as f32 + 0.5) / n as f32;
+                    henyey_greenstein(cos, g) * 2.0 * PI * (2.0 / n as f32)
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 1e-2, "{g} {integral}");
+        }
+    }
+
+    #[test]
+    fn grey_subsurface_weights() {
+        let subsurface = Subsurface {
+            albedo: Color::new(0.5, 0.5, 0.5, 1.0),
+            mean_free_path: Color::new_value(0.2, 1.0),
+            g: 0.0,
+            max_steps: 16,
+        };
+        assert_eq!(subsurface.segment_weight(0.3, true), Color::new(0.5, 0.5, 0.5, 1.0));
+        assert_eq!(subsurface.segment_weight(0.3, false), Color::WHITE);
+    }
+
+    #[test]
+    fn fog_sample_inverts_depth() {
+        let fog = HeightFog {
+            density: 0.3,
+            falloff: 0.5,
+            height: 0.0,
+            albedo: Color::WHITE,
+            g: 0.0,
+        };
+        let ray = Ray::new(Vec3(0.0, 1.0, 0.0), Vec3(0.0, 0.6, 0.8));
+        let t = fog.sample_distance(&ray, 0.3).unwrap();
+        assert!((fog.transmittance(&ray, t) - 0.7).abs() < 1e-4);
+        assert!(fog.sample_distance(&ray, 0.999).is_none());
+    }
+}
@@ -4,15 +4,31 @@ mod sphere;
 pub use plane::*;
 pub use sphere::*;
 
-use interlumen_core::Vec3;
+use interlumen_core::{Quaternion, Vec3};
 
 pub trait Hittable {
     fn dist(&self, from: Vec3) -> f32;
+    // Negative inside the object, needed for volumes
+    fn signed_dist(&self, from: Vec3) -> f32 {
+        self.dist(from)
+    }
+    // Only objects with a meaningful size return one, used by the editor
+    fn radius(&self) -> Option<f32> {
+        None
+    }
+    fn set_radius(&mut self, _radius: f32) {}
 }
 
 pub trait Position {
     fn pos(&self) -> Vec3;
     fn set_pos(&mut self, pos: Vec3);
+    // Objects without an orientation ignore it
+    fn set_rotation(&mut self, _rotation: Quaternion) {}
+    // Distance moved while the shutter is open, `pos` is where the object is when it opens
+    fn motion(&self) -> Vec3 {
+        Vec3::default()
+    }
+    fn set_motion(&mut self, _motion: Vec3) {}
 }
 
 pub trait Normal {
@@ -21,9 +37,14 @@ pub trait Normal {
 
 pub trait Texturable {
     fn uv(&self, point: Vec3) -> Vec3;
+    // Direction of increasing u, used to orient normal maps
+    fn tangent(&self, _point: Vec3) -> Vec3 {
+        Vec3(1.0, 0.0, 0.0)
+    }
     fn material(&self) -> usize {
         0
     }
+    fn set_material(&mut self, _material: usize) {}
 }
 
-pub trait Object: Hittable + Position + Normal + Texturable + Sync {}
+pub trait Object: Hittable + Position + Normal + Texturable + Send + Sync {}
@@ -1,18 +1,29 @@
 use crate::Texturable;
 
 use super::{Hittable, Normal, Object, Position};
-use interlumen_core::Vec3;
+use interlumen_core::{Quaternion, Vec3};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Plane {
     pos: Vec3,
     norm: Vec3,
     material: usize,
+    // Normal the plane was created with, rotations are relative to it
+    base_norm: Vec3,
+    rotation: Quaternion,
+    motion: Vec3,
 }
 
 impl Plane {
     pub fn new(pos: Vec3, norm: Vec3, material: usize) -> Self {
-        Self { pos, norm, material }
+        Self {
+            pos,
+            norm,
+            material,
+            base_norm: norm,
+            rotation: Quaternion::IDENTITY,
+            motion: Vec3::default(),
+        }
     }
 }
 
@@ -30,6 +41,19 @@ impl Position for Plane {
     fn set_pos(&mut self, pos: Vec3) {
         self.pos = pos
     }
+
+    fn set_rotation(&mut self, rotation: Quaternion) {
+        self.rotation = rotation;
+        self.norm = rotation.rotate(self.base_norm);
+    }
+
+    fn motion(&self) -> Vec3 {
+        self.motion
+    }
+
+    fn set_motion(&mut self, motion: Vec3) {
+        self.motion = motion;
+    }
 }
 
 impl Normal for Plane {
@@ -40,13 +64,17 @@ impl Normal for Plane {
 
 impl Texturable for Plane {
     fn uv(&self, point: Vec3) -> Vec3 {
-        let a = point - self.pos;
+        let a = self.rotation.conjugate().rotate(point - self.pos);
         Vec3(a.0, a.2, 0.0)
     }
 
     fn material(&self) -> usize {
         self.material
     }
+
+    fn set_material(&mut self, material: usize) {
+        self.material = material;
+    }
 }
 
 impl Object for Plane {}
@@ -1,13 +1,18 @@
+use std::f32::consts::PI;
+
 use crate::Texturable;
 
 use super::{Hittable, Normal, Object, Position};
-use interlumen_core::Vec3;
+use interlumen_core::{Quaternion, Vec3};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Sphere {
     pos: Vec3,
     radius: f32,
     material: usize,
+    // Only turns the texture, the shape is the same from every side
+    rotation: Quaternion,
+    motion: Vec3,
 }
 
 impl Sphere {
@@ -16,13 +21,27 @@ impl Sphere {
             pos,
             radius,
             material,
+            rotation: Quaternion::IDENTITY,
+            motion: Vec3::default(),
         }
     }
 }
 
 impl Hittable for Sphere {
     fn dist(&self, from: Vec3) -> f32 {
-        ((from - self.pos()).len() - self.radius).abs()
+        self.signed_dist(from).abs()
+    }
+
+    fn signed_dist(&self, from: Vec3) -> f32 {
+        (from - self.pos()).len() - self.radius
+    }
+
+    fn radius(&self) -> Option<f32> {
+        Some(self.radius)
+    }
+
+    fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
     }
 }
 
@@ -34,6 +53,18 @@ impl Position for Sphere {
     fn set_pos(&mut self, pos: Vec3) {
         self.pos = pos;
     }
+
+    fn set_rotation(&mut self, rotation: Quaternion) {
+        self.rotation = rotation;
+    }
+
+    fn motion(&self) -> Vec3 {
+        self.motion
+    }
+
+    fn set_motion(&mut self, motion: Vec3) {
+        self.motion = motion;
+    }
 }
 
 impl Normal for Sphere {
@@ -44,15 +75,24 @@ impl Normal for Sphere {
 
 impl Texturable for Sphere {
     fn uv(&self, point: Vec3) -> Vec3 {
-        let d = self.pos - point;
-        let u = 0.5 + d.2.atan2(d.0);
-        let v = 0.5 + d.1.asin();
+        let d = self.rotation.conjugate().rotate(point - self.pos).norm();
+        let u = 0.5 + d.2.atan2(d.0) / (2.0 * PI);
+        let v = 0.5 + d.1.clamp(-1.0, 1.0).asin() / PI;
         Vec3(u, v, 0.0)
     }
 
+    fn tangent(&self, point: Vec3) -> Vec3 {
+        let d = self.rotation.conjugate().rotate(point - self.pos);
+        self.rotation.rotate(Vec3(-d.2, 0.0, d.0))
+    }
+
     fn material(&self) -> usize {
         self.material
     }
+
+    fn set_material(&mut self, material: usize) {
+        self.material = material;
+    }
 }
 
 impl Object for Sphere {}
@@ -0,0 +1,367 @@
+use std::f32::consts::PI;
+
+use interlumen_core::{Color, Vec3};
+
+use crate::Texture;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Coords {
+    Uv,
+    #[default]
+    World,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Mapping {
+    pub coords: Coords,
+    pub scale: f32,
+}
+
+impl Mapping {
+    pub fn new(coords: Coords, scale: f32) -> Self {
+        Self { coords, scale }
+    }
+
+    pub fn apply(&self, uv: Vec3, point: Vec3) -> Vec3 {
+        match self.coords {
+            Coords::Uv => uv * self.scale,
+            Coords::World => point * self.scale,
+        }
+    }
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        Self::new(Coords::World, 1.0)
+    }
+}
+
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6b343)
+        ^ (y as u32).wrapping_mul(0xd8163841)
+        ^ (z as u32).wrapping_mul(0xcb1ab31f);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a2d39);
+    h ^ (h >> 15)
+}
+
+fn hash_unit(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    (hash(x, y, z) ^ seed.wrapping_mul(0x9e3779b9)).wrapping_mul(0x85ebca6b) as f32 / u32::MAX as f32
+}
+
+const GRADIENTS: [Vec3; 12] = [
+    Vec3(1.0, 1.0, 0.0),
+    Vec3(-1.0, 1.0, 0.0),
+    Vec3(1.0, -1.0, 0.0),
+    Vec3(-1.0, -1.0, 0.0),
+    Vec3(1.0, 0.0, 1.0),
+    Vec3(-1.0, 0.0, 1.0),
+    Vec3(1.0, 0.0, -1.0),
+    Vec3(-1.0, 0.0, -1.0),
+    Vec3(0.0, 1.0, 1.0),
+    Vec3(0.0, -1.0, 1.0),
+    Vec3(0.0, 1.0, -1.0),
+    Vec3(0.0, -1.0, -1.0),
+];
+
+fn gradient(x: i32, y: i32, z: i32) -> Vec3 {
+    GRADIENTS[(hash(x, y, z) % 12) as usize]
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Improved Perlin noise in [-1, 1]
+pub fn perlin(p: Vec3) -> f32 {
+    let (fx, fy, fz) = (p.0.floor(), p.1.floor(), p.2.floor());
+    let (x, y, z) = (fx as i32, fy as i32, fz as i32);
+    let d = Vec3(p.0 - fx, p.1 - fy, p.2 - fz);
+    let (u, v, w) = (fade(d.0), fade(d.1), fade(d.2));
+
+    let corner = |i: i32, j: i32, k: i32| {
+        gradient(x + i, y + j, z + k) * (d - Vec3(i as f32, j as f32, k as f32))
+    };
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), u);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), u);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), u);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), u);
+    lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
+}
+
+// 3D simplex noise in [-1, 1]
+pub fn simplex(p: Vec3) -> f32 {
+    const F3: f32 = 1.0 / 3.0;
+    const G3: f32 = 1.0 / 6.0;
+
+    let s = (p.0 + p.1 + p.2) * F3;
+    let (i, j, k) = ((p.0 + s).floor(), (p.1 + s).floor(), (p.2 + s).floor());
+    let t = (i + j + k) * G3;
+    let x0 = Vec3(p.0 - (i - t), p.1 - (j - t), p.2 - (k - t));
+
+    let (o1, o2) = if x0.0 >= x0.1 {
+        if x0.1 >= x0.2 {
+            ((1, 0, 0), (1, 1, 0))
+        } else if x0.0 >= x0.2 {
+            ((1, 0, 0), (1, 0, 1))
+        } else {
+            ((0, 0, 1), (1, 0, 1))
+        }
+    } else if x0.1 < x0.2 {
+        ((0, 0, 1), (0, 1, 1))
+    } else if x0.0 < x0.2 {
+        ((0, 1, 0), (0, 1, 1))
+    } else {
+        ((0, 1, 0), (1, 1, 0))
+    };
+
+    let (i, j, k) = (i as i32, j as i32, k as i32);
+    let offsets = [(0, 0, 0), o1, o2, (1, 1, 1)];
+    let mut n = 0.0;
+    for (c, (a, b, d)) in offsets.into_iter().enumerate() {
+        let x = x0 - Vec3(a as f32, b as f32, d as f32) + G3 * c as f32;
+        let falloff = 0.6 - x * x;
+        if falloff > 0.0 {
+            n += falloff.powi(4) * (gradient(i + a, j + b, k + d) * x);
+        }
+    }
+    32.0 * n
+}
+
+// Distance to the closest feature point (F1), roughly in [0, 1]
+pub fn worley(p: Vec3) -> f32 {
+    let (x, y, z) = (p.0.floor() as i32, p.1.floor() as i32, p.2.floor() as i32);
+    let mut closest = f32::MAX;
+    for i in -1..=1 {
+        for j in -1..=1 {
+            for k in -1..=1 {
+                let (cx, cy, cz) = (x + i, y + j, z + k);
+                let feature = Vec3(
+                    cx as f32 + hash_unit(cx, cy, cz, 1),
+                    cy as f32 + hash_unit(cx, cy, cz, 2),
+                    cz as f32 + hash_unit(cx, cy, cz, 3),
+                );
+                closest = closest.min((feature - p).len());
+            }
+        }
+    }
+    closest.min(1.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseBasis {
+    Perlin,
+    Simplex,
+    Worley,
+}
+
+impl NoiseBasis {
+    // Noise value remapped to [0, 1]
+    pub fn eval(&self, p: Vec3) -> f32 {
+        match self {
+            NoiseBasis::Perlin => (perlin(p) * 0.5 + 0.5).clamp(0.0, 1.0),
+            NoiseBasis::Simplex => (simplex(p) * 0.5 + 0.5).clamp(0.0, 1.0),
+            NoiseBasis::Worley => worley(p),
+        }
+    }
+}
+
+// Fractal Brownian motion, sums octaves of the basis with decreasing amplitude
+pub fn fbm(basis: NoiseBasis, p: Vec3, octaves: usize, lacunarity: f32, gain: f32) -> f32 {
+    let mut sum = 0.0;
+    let mut norm = 0.0;
+    let mut amplitude = 1.0;
+    let mut p = p;
+    for _ in 0..octaves.max(1) {
+        sum += basis.eval(p) * amplitude;
+        norm += amplitude;
+        amplitude *= gain;
+        p *= lacunarity;
+    }
+    sum / norm
+}
+
+fn gray(value: f32) -> Color {
+    Color::new_value(value, 1.0)
+}
+
+pub struct ConstantTexture(pub Color);
+
+impl Texture for ConstantTexture {
+    fn sample(&self, _uv: Vec3, _point: Vec3) -> Color {
+        self.0
+    }
+}
+
+pub struct NoiseTexture {
+    pub mapping: Mapping,
+    pub basis: NoiseBasis,
+    pub octaves: usize,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl Texture for NoiseTexture {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        let p = self.mapping.apply(uv, point);
+        gray(fbm(self.basis, p, self.octaves, self.lacunarity, self.gain))
+    }
+}
+
+// Veins from a sine wave distorted by turbulence
+pub struct MarbleTexture {
+    pub mapping: Mapping,
+    pub frequency: f32,
+    pub turbulence: f32,
+    pub octaves: usize,
+}
+
+impl Texture for MarbleTexture {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        let p = self.mapping.apply(uv, point);
+        let noise = fbm(NoiseBasis::Perlin, p, self.octaves, 2.0, 0.5) * 2.0 - 1.0;
+        gray(((p.0 * self.frequency + noise * self.turbulence) * PI).sin() * 0.5 + 0.5)
+    }
+}
+
+// Concentric rings around the Y axis
+pub struct WoodTexture {
+    pub mapping: Mapping,
+    pub frequency: f32,
+    pub turbulence: f32,
+}
+
+impl Texture for WoodTexture {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        let p = self.mapping.apply(uv, point);
+        let radius = (p.0 * p.0 + p.2 * p.2).sqrt();
+        let rings = radius * self.frequency + perlin(p) * self.turbulence;
+        gray(rings - rings.floor())
+    }
+}
+
+pub struct StripesTexture {
+    pub mapping: Mapping,
+    pub frequency: f32,
+    // Fraction of each period covered by the stripe
+    pub width: f32,
+}
+
+impl Texture for StripesTexture {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        let x = self.mapping.apply(uv, point).0 * self.frequency;
+        gray(if x - x.floor() < self.width { 1.0 } else { 0.0 })
+    }
+}
+
+// Linear ramp along one axis, clamped to [0, 1]
+pub struct GradientTexture {
+    pub mapping: Mapping,
+    pub axis: usize,
+}
+
+impl Texture for GradientTexture {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        let p = self.mapping.apply(uv, point);
+        let t = match self.axis {
+            0 => p.0,
+            1 => p.1,
+            _ => p.2,
+        };
+        gray(t.clamp(0.0, 1.0))
+    }
+}
+
+// Maps the red channel of `input` through sorted color stops
+pub struct ColorRamp {
+    pub input: Box<dyn Texture>,
+    pub stops: Vec<(f32, Color)>,
+}
+
+impl ColorRamp {
+    pub fn eval(&self, t: f32) -> Color {
+        let Some(first) = self.stops.first() else {
+            return gray(t);
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+            if t <= t1 {
+                let f = (t - t0) / (t1 - t0).max(1e-6);
+                return c0 * (1.0 - f) + c1 * f;
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+impl Texture for ColorRamp {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        self.eval(self.input.sample(uv, point).r)
+    }
+}
+
+// Blends `a` and `b` by the red channel of `factor`
+pub struct MixTexture {
+    pub a: Box<dyn Texture>,
+    pub b: Box<dyn Texture>,
+    pub factor: Box<dyn Texture>,
+}
+
+impl Texture for MixTexture {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        let f = self.factor.sample(uv, point).r.clamp(0.0, 1.0);
+        self.a.sample(uv, point) * (1.0 - f) + self.b.sample(uv, point) * f
+    }
+}
+
+pub struct MultiplyTexture {
+    pub a: Box<dyn Texture>,
+    pub b: Box<dyn Texture>,
+}
+
+impl Texture for MultiplyTexture {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color {
+        self.a.sample(uv, point) * self.b.sample(uv, point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_in_range() {
+        for i in 0..1000 {
+            let p = Vec3(i as f32 * 0.173, i as f32 * 0.311 - 40.0, i as f32 * -0.057);
+            for basis in [NoiseBasis::Perlin, NoiseBasis::Simplex, NoiseBasis::Worley] {
+                let v = basis.eval(p);
+                assert!((0.0..=1.0).contains(&v), "{basis:?} {v}");
+            }
+        }
+    }
+
+    #[test]
+    fn perlin_zero_on_lattice() {
+        assert_eq!(perlin(Vec3(3.0, -2.0, 7.0)), 0.0);
+    }
+
+    #[test]
+    fn color_ramp() {
+        let ramp = ColorRamp {
+            input: Box::new(ConstantTexture(Color::BLACK)),
+            stops: vec![(0.0, Color::BLACK), (1.0, Color::WHITE)],
+        };
+        assert_eq!(ramp.eval(-1.0), Color::BLACK);
+        assert_eq!(ramp.eval(0.5), Color::GRAY);
+        assert_eq!(ramp.eval(2.0), Color::WHITE);
+    }
+}
@@ -1,18 +1,52 @@
-use crate::{Camera, BasicMaterial, Material, Object, Scene};
+use std::cell::Cell;
+use std::f32::consts::PI;
+
+use crate::{
+    henyey_greenstein, sample_cosine, sample_henyey_greenstein, BasicMaterial, Bsdf, Camera,
+    Environment, HeightFog, Light, Material, Medium, Object, RenderMode, Sampler, SamplerKind,
+    Scene, Subsurface, TileOrder,
+};
 use interlumen_core::{Color, Ray, Vec3};
 
+// What the renderer reads from the driver, borrowed for the duration of a pass
+#[derive(Clone, Copy)]
+pub struct SceneData<'a> {
+    pub scene: &'a Scene,
+    pub materials: &'a [Box<dyn Material>],
+    pub lights: &'a [Box<dyn Light>],
+    pub environment: &'a Environment,
+    pub fog: &'a Option<HeightFog>,
+    pub camera: &'a Camera,
+}
+
 pub struct HitPayload<'a> {
     pub distance: f32,
     pub point: Vec3,
     pub object: &'a Box<dyn Object>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct RendererSettings {
     pub max_iter: usize,
     pub ray_depth: usize,
     pub max_dist: f32,
     pub hit_thres: f32,
     pub pixel_ratio: f32,
+    // Same seed and settings always produce the same image
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    // Pixels whose relative standard error drops below this stop receiving samples,
+    // 0 samples every pixel on every pass
+    pub target_error: f32,
+    // Samples taken before a pixel's error estimate is trusted
+    pub min_samples: usize,
+    // Edge length of the square tiles the image is rendered in
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    pub render_mode: RenderMode,
+    // Seconds the shutter stays open, objects and the camera are blurred along their
+    // motion over it. 0 disables motion blur.
+    pub shutter: f32,
 }
 
 impl RendererSettings {
@@ -23,67 +57,341 @@ impl RendererSettings {
             max_dist: 200.0,
             hit_thres: 0.01,
             pixel_ratio: 1.0,
+            seed: 0,
+            sampler: SamplerKind::default(),
+            target_error: 0.0,
+            min_samples: 16,
+            tile_size: 32,
+            tile_order: TileOrder::default(),
+            render_mode: RenderMode::default(),
+            shutter: 0.0,
         }
     }
 }
 
+thread_local! {
+    // Rays traced on this thread, the driver reads it to report throughput
+    static RAYS_TRACED: Cell<u64> = const { Cell::new(0) };
+}
+
+fn count_ray() {
+    RAYS_TRACED.with(|rays| rays.set(rays.get() + 1));
+}
+
 pub struct Renderer {}
 
 impl Renderer {
     pub const FALLBACK_MATERIAL: BasicMaterial = BasicMaterial { albedo: Color::new(1.0, 0.0, 1.0, 1.0), emit: Color::BLACK, roughness: 0.0};
 
+    pub fn rays_traced() -> u64 {
+        RAYS_TRACED.with(Cell::get)
+    }
+
+    // Point relative to where `obj` is at `time`, objects are stored where the shutter opens
+    pub fn local(obj: &dyn Object, point: Vec3, time: f32) -> Vec3 {
+        point - obj.motion() * time
+    }
+
     pub fn render_pixel(
         settings: &RendererSettings,
-        scene: &Scene,
-        materials: &Vec<Box<dyn Material>>,
+        data: &SceneData,
         x: usize,
         y: usize,
         screen_w: usize,
         screen_h: usize,
-        camera: &Camera,
+        sampler: &mut dyn Sampler,
     ) -> Color {
+        if settings.render_mode != RenderMode::Path {
+            return Renderer::debug_pixel(settings, data, x, y, screen_w, screen_h, sampler);
+        }
+        let &SceneData { scene, materials, environment, fog, camera, .. } = data;
         let mut incoming_color = Color::BLACK;
         let mut ray_color = Color::WHITE;
+        let mut bounce_pdf = 0.0;
+        let mut t_min = 1.0;
 
-        let mut pixel_ray = camera.get_pixel_ray(x, y, screen_w, screen_h, settings.pixel_ratio);
+        // Every path happens at a single moment while the shutter is open
+        let time = if settings.shutter > 0.0 { sampler.next_1d() } else { 0.0 };
+        let mut pixel_ray = camera.at_time(time).sample_pixel_ray(x, y, screen_w, screen_h, settings.pixel_ratio, sampler);
+        pixel_ray.time = time;
         for _ in 0..settings.ray_depth {
-            if let Some(payload) = Renderer::closest_hit(settings, &pixel_ray, scene) {
+            let hit = Renderer::closest_hit_from(settings, &pixel_ray, scene, t_min);
+            let segment = hit.as_ref().map_or(f32::MAX, |p| p.distance);
+
+            // Free-flight sampling through the fog and the volume the ray is travelling in
+            let mut event: Option<(f32, Color, f32)> = None;
+            if let Some(fog) = fog {
+                if let Some(t) = fog.sample_distance(&pixel_ray, sampler.next_1d()) {
+                    if t < segment {
+                        event = Some((t, fog.albedo, fog.g));
+                    }
+                }
+            }
+            let start = pixel_ray.origin + pixel_ray.dir * settings.hit_thres;
+            if let Some(medium) = Renderer::medium_at(scene, materials, start, time) {
+                if let Some(t) = medium.sample_distance(sampler.next_1d()) {
+                    if t < event.map_or(segment, |e| e.0) {
+                        event = Some((t, medium.albedo(), medium.g));
+                    }
+                }
+            }
+
+            if let Some((t, albedo, g)) = event {
+                let point = pixel_ray.origin + pixel_ray.dir * t;
+                ray_color *= albedo;
+                let scattering = Scattering::Medium { dir: pixel_ray.dir, g };
+                incoming_color += Renderer::direct_light(settings, data, point, time, &scattering, sampler) * ray_color;
+                let (u1, u2) = sampler.next_2d();
+                let new_dir = sample_henyey_greenstein(pixel_ray.dir, g, u1, u2);
+                bounce_pdf = scattering.pdf(new_dir);
+                pixel_ray = Ray::with_time(point, new_dir, time);
+                t_min = 0.0;
+                continue;
+            }
+
+            if let Some(payload) = hit {
                 let hit = payload.point;
                 let obj = payload.object;
 
-                let norm = obj.norm(hit);
-                if let Some(a) = materials.get(obj.material()) {
-                    let color = a.get_color(obj.uv(hit));
-                    incoming_color += color.emit * ray_color;
-                    ray_color *= color.albedo * (1.0 / (payload.distance*payload.distance));
-                    let rand_dir = (Vec3(rand::random(), rand::random(), rand::random()) * color.roughness + norm).norm();
-                    let new_dir = (rand_dir * (norm * rand_dir).signum()).norm();
-                    pixel_ray = Ray {
-                        origin: hit + norm*0.001,
-                        dir: new_dir,
+                // Volume boundaries are invisible, the ray just enters or leaves the medium
+                if Renderer::medium_of(obj.as_ref(), materials).is_some() {
+                    pixel_ray = Ray::with_time(hit + pixel_ray.dir * settings.hit_thres * 2.0, pixel_ray.dir, time);
+                    t_min = 0.0;
+                    continue;
+                }
+
+                let local = Renderer::local(obj.as_ref(), hit, time);
+                let geom_norm = obj.norm(local);
+
+                // Subsurface scattering, light re-emerges diffusely somewhere else on the object
+                if let Some(subsurface) = materials.get(obj.material()).and_then(|m| m.subsurface()) {
+                    let Some((exit, exit_norm, weight)) =
+                        Renderer::subsurface_walk(settings, obj.as_ref(), subsurface, hit, geom_norm, time, sampler)
+                    else {
+                        break;
                     };
-                }               
+                    ray_color *= weight;
+                    let scattering = Scattering::Diffuse(exit_norm);
+                    incoming_color += Renderer::direct_light(settings, data, exit, time, &scattering, sampler) * ray_color;
+                    let (u1, u2) = sampler.next_2d();
+                    let new_dir = sample_cosine(exit_norm, u1, u2);
+                    bounce_pdf = scattering.pdf(new_dir);
+                    pixel_ray = Ray::with_time(exit + exit_norm * settings.hit_thres * 2.0, new_dir, time);
+                    t_min = 0.0;
+                    continue;
+                }
 
+                let material = materials
+                    .get(obj.material())
+                    .map_or(&Renderer::FALLBACK_MATERIAL as &dyn Material, |m| m.as_ref());
+                let color = material.get_color(obj.uv(local), local);
+                let norm = color.shading_normal(geom_norm, obj.tangent(local));
+                let wo = -pixel_ray.dir;
+                let bsdf = Bsdf::new(&color, norm, geom_norm, obj.tangent(local), wo);
+                let scattering = Scattering::Surface { bsdf: &bsdf, wo };
+                incoming_color += color.emit * ray_color;
+                incoming_color += Renderer::direct_light(settings, data, hit, time, &scattering, sampler) * ray_color;
+                let lobe = sampler.next_1d();
+                let (u1, u2) = sampler.next_2d();
+                let Some(sample) = bsdf.sample(wo, [lobe, u1, u2]) else {
+                    break;
+                };
+                ray_color *= sample.weight;
+                bounce_pdf = sample.pdf;
+                // Refracted rays continue on the other side of the surface, far enough
+                // from it that marching doesn't immediately hit it again
+                let side = if sample.dir * geom_norm >= 0.0 { 1.0 } else { -1.0 };
+                pixel_ray = Ray::with_time(hit + geom_norm * side * settings.hit_thres * 3.0, sample.dir, time);
+                t_min = 0.0;
             } else {
-                // incoming_color += Color::new(4.5, 4.5, 4.5, 1.0) * ray_color;
+                // Balance against the environment samples taken at the previous hit
+                let env_pdf = environment.pdf(pixel_ray.dir);
+                let weight = if bounce_pdf > 0.0 && env_pdf > 0.0 {
+                    bounce_pdf / (bounce_pdf + env_pdf)
+                } else {
+                    1.0
+                };
+                incoming_color += environment.radiance(pixel_ray.dir) * ray_color * weight;
                 break;
             }
         }
         incoming_color
     }
 
+    // Random walk inside `obj` starting from the surface point, returns where the light
+    // leaves the object with the outward normal and the path weight, or `None` when absorbed
+    pub fn subsurface_walk(
+        settings: &RendererSettings,
+        obj: &dyn Object,
+        subsurface: &Subsurface,
+        point: Vec3,
+        norm: Vec3,
+        time: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Vec3, Vec3, Color)> {
+        let mut weight = Color::WHITE;
+        let mut pos = point - norm * settings.hit_thres * 2.0;
+        let (u1, u2) = sampler.next_2d();
+        let mut dir = sample_cosine(-norm, u1, u2);
+        for _ in 0..subsurface.max_steps {
+            let (u1, u2) = sampler.next_2d();
+            let t = subsurface.sample_distance(u1, u2);
+            let ray = Ray::with_time(pos, dir, time);
+            if let Some(exit) = Renderer::exit_distance(settings, obj, &ray, t) {
+                weight *= subsurface.segment_weight(exit, false);
+                let exit_point = pos + dir * exit;
+                return Some((exit_point, obj.norm(Renderer::local(obj, exit_point, time)), weight));
+            }
+            weight *= subsurface.segment_weight(t, true);
+            pos += dir * t;
+            let (u1, u2) = sampler.next_2d();
+            dir = sample_henyey_greenstein(dir, subsurface.g, u1, u2);
+        }
+        None
+    }
+
+    // Distance from a point inside `obj` to its boundary along the ray, if closer than `max_t`
+    pub fn exit_distance(settings: &RendererSettings, obj: &dyn Object, ray: &Ray, max_t: f32) -> Option<f32> {
+        count_ray();
+        let mut t = 0.0;
+        for _ in 0..=settings.max_iter {
+            let dist = -obj.signed_dist(Renderer::local(obj, ray.origin + ray.dir * t, ray.time));
+            if dist <= settings.hit_thres {
+                return Some(t);
+            }
+            t += dist;
+            if t >= max_t {
+                return None;
+            }
+        }
+        None
+    }
+
+    pub fn medium_of<'a>(obj: &dyn Object, materials: &'a [Box<dyn Material>]) -> Option<&'a Medium> {
+        materials.get(obj.material()).and_then(|m| m.medium())
+    }
+
+    // Medium of the volume object containing `point`, if any
+    pub fn medium_at<'a>(scene: &Scene, materials: &'a [Box<dyn Material>], point: Vec3, time: f32) -> Option<&'a Medium> {
+        scene
+            .iter()
+            .filter(|obj| obj.signed_dist(Renderer::local(obj.as_ref(), point, time)) < 0.0)
+            .find_map(|obj| Renderer::medium_of(obj.as_ref(), materials))
+    }
+
+    pub fn direct_light(
+        settings: &RendererSettings,
+        data: &SceneData,
+        point: Vec3,
+        time: f32,
+        scattering: &Scattering,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let &SceneData { lights, environment, .. } = data;
+        let mut light_color = Color::BLACK;
+        let origin = point + scattering.offset() * settings.hit_thres * 2.0;
+        for light in lights {
+            if let Some(sample) = light.sample(point, sampler) {
+                let response = scattering.eval(sample.dir);
+                if response.r <= 0.0 && response.g <= 0.0 && response.b <= 0.0 {
+                    continue;
+                }
+                let shadow_ray = Ray::with_time(origin, sample.dir, time);
+                let visibility = Renderer::transmittance(settings, data, &shadow_ray, sample.dist);
+                light_color += sample.radiance * response * visibility;
+            }
+        }
+        if let Some((dir, radiance, pdf)) = environment.sample(sampler) {
+            let response = scattering.eval(dir);
+            if pdf > 0.0 && (response.r > 0.0 || response.g > 0.0 || response.b > 0.0) {
+                let shadow_ray = Ray::with_time(origin, dir, time);
+                let visibility = Renderer::transmittance(settings, data, &shadow_ray, f32::MAX);
+                let weight = pdf / (pdf + scattering.pdf(dir));
+                light_color += radiance * response * (visibility / pdf * weight);
+            }
+        }
+        light_color
+    }
+
+    // Fraction of light passing along the ray, zero when an opaque object is in the way
+    pub fn transmittance(settings: &RendererSettings, data: &SceneData, ray: &Ray, max_dist: f32) -> f32 {
+        let &SceneData { scene, materials, fog, .. } = data;
+        count_ray();
+        let mut t = settings.hit_thres;
+        let mut optical_depth = 0.0;
+        for _ in 0..=settings.max_iter {
+            if t >= max_dist {
+                break;
+            }
+            let point = ray.origin + ray.dir * t;
+            let mut step = f32::MAX;
+            let mut extinction = 0.0;
+            for obj in scene {
+                let point = Renderer::local(obj.as_ref(), point, ray.time);
+                let dist = obj.dist(point);
+                match Renderer::medium_of(obj.as_ref(), materials) {
+                    Some(medium) => {
+                        if obj.signed_dist(point) < 0.0 {
+                            extinction += medium.extinction();
+                        }
+                        step = step.min(dist.max(settings.hit_thres));
+                    }
+                    None => {
+                        if dist <= settings.hit_thres {
+                            return 0.0;
+                        }
+                        step = step.min(dist);
+                    }
+                }
+            }
+            if step > settings.max_dist {
+                break;
+            }
+            let step = step.min(max_dist - t);
+            optical_depth += extinction * step;
+            t += step;
+        }
+        let fog_transmittance = fog
+            .as_ref()
+            .map_or(1.0, |fog| fog.transmittance(ray, max_dist.min(settings.max_dist)));
+        (-optical_depth).exp() * fog_transmittance
+    }
+
     pub fn closest_hit<'a>(
         settings: &RendererSettings,
         ray: &Ray,
         scene: &'a Vec<Box<dyn Object>>,
     ) -> Option<HitPayload<'a>> {
-        let mut t = 1.0;
-        let mut hit = &scene[0];
+        Renderer::closest_hit_from(settings, ray, scene, 1.0)
+    }
+
+    pub fn closest_hit_from<'a>(
+        settings: &RendererSettings,
+        ray: &Ray,
+        scene: &'a Vec<Box<dyn Object>>,
+        t_min: f32,
+    ) -> Option<HitPayload<'a>> {
+        Renderer::march(settings, ray, scene, t_min).0
+    }
+
+    // Closest hit along with the number of sphere-tracing steps taken
+    pub fn march<'a>(
+        settings: &RendererSettings,
+        ray: &Ray,
+        scene: &'a Vec<Box<dyn Object>>,
+        t_min: f32,
+    ) -> (Option<HitPayload<'a>>, usize) {
+        count_ray();
+        let mut t = t_min;
+        let Some(mut hit) = scene.first() else {
+            return (None, 0);
+        };
         let mut i = 0;
         while i <= settings.max_iter {
             let mut dist: f32 = f32::MAX;
+            let point = ray.origin + ray.dir * t;
             for obj in scene {
-                let d = obj.dist(ray.origin + ray.dir * t);
+                let d = obj.dist(Renderer::local(obj.as_ref(), point, ray.time));
                 if d <= dist {
                     dist = d;
                     hit = obj;
@@ -92,11 +400,49 @@ impl Renderer {
             i += 1;
             t += dist;
             if dist <= settings.hit_thres {
-                return Some(HitPayload{distance: t, point: ray.origin + ray.dir * t, object: hit});
+                return (Some(HitPayload{distance: t, point: ray.origin + ray.dir * t, object: hit}), i);
             } else if dist > settings.max_dist {
                 break;
             }
         }
-        None
+        (None, i)
+    }
+}
+
+// How a point responds to light arriving from a direction
+pub enum Scattering<'a> {
+    // Lambertian surface with the given normal
+    Diffuse(Vec3),
+    // Surface BSDF seen from the outgoing direction `wo`
+    Surface { bsdf: &'a Bsdf, wo: Vec3 },
+    // Phase function of a medium for light travelling along `dir`
+    Medium { dir: Vec3, g: f32 },
+}
+
+impl Scattering<'_> {
+    // Response to light arriving from `dir`, including the cosine term for surfaces
+    pub fn eval(&self, dir: Vec3) -> Color {
+        match self {
+            Scattering::Diffuse(norm) => Color::new_value((*norm * dir).max(0.0) / PI, 1.0),
+            Scattering::Surface { bsdf, wo } => bsdf.eval(*wo, dir),
+            Scattering::Medium { dir: d, g } => Color::new_value(henyey_greenstein(*d * dir, *g), 1.0),
+        }
+    }
+
+    pub fn pdf(&self, dir: Vec3) -> f32 {
+        match self {
+            Scattering::Diffuse(norm) => (*norm * dir).max(0.0) / PI,
+            Scattering::Surface { bsdf, wo } => bsdf.pdf(*wo, dir),
+            Scattering::Medium { dir: d, g } => henyey_greenstein(*d * dir, *g),
+        }
+    }
+
+    // Direction to push shadow ray origins away from the surface
+    pub fn offset(&self) -> Vec3 {
+        match self {
+            Scattering::Diffuse(norm) => *norm,
+            Scattering::Surface { bsdf, .. } => bsdf.normal(),
+            Scattering::Medium { .. } => Vec3::default(),
+        }
     }
 }
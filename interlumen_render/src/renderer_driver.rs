@@ -1,55 +1,474 @@
-use crate::{Material, Scene, Camera, Renderer, RendererSettings};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{tile_grid, Environment, HeightFog, Light, Material, Scene, SceneData, Camera, Renderer, RendererSettings, Tile};
 use interlumen_core::Color;
 
 use rayon::prelude::*;
 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConvergenceStats {
+    pub converged_pixels: usize,
+    pub total_pixels: usize,
+    // Relative standard error averaged over and maximum across all pixels
+    pub mean_error: f32,
+    pub max_error: f32,
+    pub total_samples: usize,
+}
+
+impl ConvergenceStats {
+    pub fn converged_fraction(&self) -> f32 {
+        self.converged_pixels as f32 / self.total_pixels.max(1) as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderProgress {
+    // Tiles finished in the current pass
+    pub tiles_done: usize,
+    pub tiles_total: usize,
+    // Completed passes over the whole image
+    pub passes: usize,
+}
+
+impl RenderProgress {
+    pub fn fraction(&self) -> f32 {
+        self.tiles_done as f32 / self.tiles_total.max(1) as f32
+    }
+
+    pub fn pass_complete(&self) -> bool {
+        self.tiles_done == self.tiles_total
+    }
+}
+
+// Change counters for everything that affects the rendered image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Versions {
+    pub scene: u64,
+    pub materials: u64,
+    // Lights, environment and fog
+    pub lights: u64,
+    pub camera: u64,
+    pub settings: u64,
+}
+
+// Relative standard error of a pixel mean from its luminance sums
+fn pixel_error(sum: f32, sum_sq: f32, samples: u32) -> f32 {
+    if samples < 2 {
+        return f32::INFINITY;
+    }
+    let n = samples as f32;
+    let mean = sum / n;
+    let variance = ((sum_sq / n - mean * mean) * n / (n - 1.0)).max(0.0);
+    (variance / n).sqrt() / mean.max(1e-3)
+}
+
 pub struct RendererDriver {
-    pub materials: Vec<Box<dyn Material>>,
+    // Only changed through the `*_mut` accessors, which restart the accumulation
+    materials: Vec<Box<dyn Material>>,
+    lights: Vec<Box<dyn Light>>,
+    environment: Environment,
+    fog: Option<HeightFog>,
     pub camera: Camera,
-    pub scene: Scene,
+    scene: Scene,
     pub settings: RendererSettings,
     pub accum_buffer: Vec<Color>,
+    // Sum of squared luminance and number of samples taken for every pixel
+    pub moment_buffer: Vec<f32>,
+    pub sample_counts: Vec<u32>,
     pub accum_steps: usize,
+    // Total over the driver's lifetime, not reset with the accumulation buffer
+    pub rays_traced: u64,
+    pub versions: Versions,
+    // Tiles of the current pass in rendering order and the next one to render
+    tiles: Vec<Tile>,
+    next_tile: usize,
+    // State the accumulated samples were rendered with
+    accum_size: (usize, usize),
+    accum_versions: Versions,
+    accum_camera: Camera,
+    accum_settings: RendererSettings,
 }
 
 impl RendererDriver {
     pub fn new() -> Self {
         Self {
             materials: Vec::new(),
+            lights: Vec::new(),
+            environment: Environment::Color(Color::BLACK),
+            fog: None,
             scene: Vec::new(),
             camera: Camera::unit(),
             settings: RendererSettings::new(),
             accum_buffer: Vec::new(),
+            moment_buffer: Vec::new(),
+            sample_counts: Vec::new(),
             accum_steps: 0,
+            rays_traced: 0,
+            versions: Versions::default(),
+            tiles: Vec::new(),
+            next_tile: 0,
+            accum_size: (0, 0),
+            accum_versions: Versions::default(),
+            accum_camera: Camera::unit(),
+            accum_settings: RendererSettings::new(),
+        }
+    }
+
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    pub fn materials(&self) -> &[Box<dyn Material>] {
+        &self.materials
+    }
+
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    pub fn fog(&self) -> Option<&HeightFog> {
+        self.fog.as_ref()
+    }
+
+    pub fn scene_data(&self) -> SceneData<'_> {
+        SceneData {
+            scene: &self.scene,
+            materials: &self.materials,
+            lights: &self.lights,
+            environment: &self.environment,
+            fog: &self.fog,
+            camera: &self.camera,
         }
     }
 
+    // Accessors that invalidate the accumulated image, camera and settings
+    // changes are also picked up when the fields are written directly
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        self.versions.scene += 1;
+        &mut self.scene
+    }
+
+    pub fn materials_mut(&mut self) -> &mut Vec<Box<dyn Material>> {
+        self.versions.materials += 1;
+        &mut self.materials
+    }
+
+    pub fn lights_mut(&mut self) -> &mut Vec<Box<dyn Light>> {
+        self.versions.lights += 1;
+        &mut self.lights
+    }
+
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        self.versions.lights += 1;
+        &mut self.environment
+    }
+
+    pub fn fog_mut(&mut self) -> &mut Option<HeightFog> {
+        self.versions.lights += 1;
+        &mut self.fog
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        self.versions.camera += 1;
+        &mut self.camera
+    }
+
+    pub fn settings_mut(&mut self) -> &mut RendererSettings {
+        self.versions.settings += 1;
+        &mut self.settings
+    }
+
+    pub fn is_dirty(&self, width: usize, height: usize) -> bool {
+        self.accum_size != (width, height)
+            || self.versions != self.accum_versions
+            || self.camera != self.accum_camera
+            || self.settings != self.accum_settings
+    }
+
     pub fn init_accum_buffer(&mut self, width: usize, height: usize) {
         self.accum_buffer.clear();
         self.accum_buffer.resize(width*height, Color::BLACK);
+        self.moment_buffer.clear();
+        self.moment_buffer.resize(width*height, 0.0);
+        self.sample_counts.clear();
+        self.sample_counts.resize(width*height, 0);
+        self.accum_steps = 0;
+        self.tiles = tile_grid(width, height, self.settings.tile_size, self.settings.tile_order);
+        self.next_tile = 0;
+        self.accum_size = (width, height);
+        self.accum_versions = self.versions;
+        self.accum_camera = self.camera.clone();
+        self.accum_settings = self.settings.clone();
+    }
+
+    fn converged(&self, sum: Color, sum_sq: f32, samples: u32) -> bool {
+        self.settings.target_error > 0.0
+            && samples as usize >= self.settings.min_samples
+            && pixel_error(sum.luminance(), sum_sq, samples) < self.settings.target_error
     }
 
+    // Adds one sample to every pixel that hasn't converged yet, starting over
+    // when anything changed since the last call. Finishes the pass in progress
+    // if `render_tiles` stopped halfway.
     pub fn append_to_accum_buffer(&mut self, width: usize, height: usize) {
-        self.accum_buffer.par_iter_mut().enumerate().for_each(|(pos, i)| {
-            let (x, y) = (pos % width, pos / width);
-            let color = Renderer::render_pixel(&self.settings, &self.scene, &self.materials, x, y, width, height, &self.camera);
-            *i += color;
-        });
+        let cancel = AtomicBool::new(false);
+        while !self.render_tiles(width, height, None, &cancel).pass_complete() {}
+    }
+
+    pub fn progress(&self) -> RenderProgress {
+        RenderProgress {
+            tiles_done: self.next_tile,
+            tiles_total: self.tiles.len(),
+            passes: self.accum_steps,
+        }
+    }
+
+    // Renders tiles of the current pass until it's finished, `budget` runs out or `cancel`
+    // is set. Tiles are rendered in parallel batches and never left half done.
+    pub fn render_tiles(&mut self, width: usize, height: usize, budget: Option<Duration>, cancel: &AtomicBool) -> RenderProgress {
+        let start = Instant::now();
+        if self.is_dirty(width, height) {
+            self.init_accum_buffer(width, height);
+        }
+        if self.next_tile == self.tiles.len() {
+            self.next_tile = 0;
+        }
+        let batch = rayon::current_num_threads() * 2;
+        while self.next_tile < self.tiles.len() {
+            if cancel.load(Ordering::Relaxed) || budget.is_some_and(|budget| start.elapsed() >= budget) {
+                return self.progress();
+            }
+            let end = (self.next_tile + batch).min(self.tiles.len());
+            let results: Vec<(Vec<(usize, Color)>, u64)> = self.tiles[self.next_tile..end].par_iter().map(|tile| self.render_tile(tile, width, height)).collect();
+            self.rays_traced += results.iter().map(|(_, rays)| rays).sum::<u64>();
+            for (pos, color) in results.into_iter().flat_map(|(colors, _)| colors) {
+                self.accum_buffer[pos] += color;
+                self.moment_buffer[pos] += color.luminance() * color.luminance();
+                self.sample_counts[pos] += 1;
+            }
+            self.next_tile = end;
+        }
         self.accum_steps += 1;
+        self.progress()
+    }
+
+    // Returns the new samples and the number of rays traced for them
+    fn render_tile(&self, tile: &Tile, width: usize, height: usize) -> (Vec<(usize, Color)>, u64) {
+        let rays = Renderer::rays_traced();
+        let data = self.scene_data();
+        let mut colors = Vec::with_capacity(tile.width * tile.height);
+        for y in tile.y..tile.y + tile.height {
+            for x in tile.x..tile.x + tile.width {
+                let pos = y * width + x;
+                let samples = self.sample_counts[pos];
+                if self.converged(self.accum_buffer[pos], self.moment_buffer[pos], samples) {
+                    continue;
+                }
+                let mut sampler = self.settings.sampler.create(x, y, samples as usize, self.settings.seed);
+                let color = Renderer::render_pixel(&self.settings, &data, x, y, width, height, sampler.as_mut());
+                colors.push((pos, color));
+            }
+        }
+        (colors, Renderer::rays_traced() - rays)
+    }
+
+    // Keeps adding samples until every pixel reaches `settings.target_error` or the time runs out
+    pub fn render_converged(&mut self, width: usize, height: usize, budget: Duration) -> ConvergenceStats {
+        let start = Instant::now();
+        loop {
+            self.append_to_accum_buffer(width, height);
+            let stats = self.convergence();
+            if stats.converged_pixels == stats.total_pixels || start.elapsed() >= budget {
+                return stats;
+            }
+        }
+    }
+
+    pub fn convergence(&self) -> ConvergenceStats {
+        let errors: Vec<f32> = self.accum_buffer.par_iter().zip(self.moment_buffer.par_iter()).zip(self.sample_counts.par_iter()).map(|((sum, sum_sq), samples)| {
+            pixel_error(sum.luminance(), *sum_sq, *samples)
+        }).collect();
+        let converged_pixels = (0..errors.len()).filter(|&pos| {
+            self.converged(self.accum_buffer[pos], self.moment_buffer[pos], self.sample_counts[pos])
+        }).count();
+        let finite: Vec<f32> = errors.iter().copied().filter(|e| e.is_finite()).collect();
+        ConvergenceStats {
+            converged_pixels,
+            total_pixels: errors.len(),
+            mean_error: finite.iter().sum::<f32>() / finite.len().max(1) as f32,
+            max_error: finite.iter().copied().fold(0.0, f32::max),
+            total_samples: self.sample_counts.iter().map(|&n| n as usize).sum(),
+        }
     }
 
     pub fn show_accum_buffer(&self) -> Vec<Color> {
-        self.accum_buffer.par_iter().map(|i| {
-            let color = *i / self.accum_steps as f32;
+        self.accum_buffer.par_iter().zip(self.sample_counts.par_iter()).map(|(i, samples)| {
+            let color = *i / (*samples).max(1) as f32;
             color.pow(2.4).clamp(0.0, 1.0)
         }).collect()
     }
 
-    pub fn draw_image(&self, width: usize, height: usize) -> Vec<Color> {
+    // Index of the object seen through the given pixel
+    pub fn pick(&self, x: usize, y: usize, width: usize, height: usize) -> Option<usize> {
+        let ray = self.camera.get_pixel_ray(x, y, width, height, self.settings.pixel_ratio);
+        let hit = Renderer::closest_hit(&self.settings, &ray, &self.scene)?;
+        self.scene.iter().position(|obj| std::ptr::eq(obj, hit.object))
+    }
+
+    // Single sample per pixel, `frame` picks the sample so consecutive frames get different noise
+    pub fn draw_image(&self, width: usize, height: usize, frame: usize) -> Vec<Color> {
+        let data = self.scene_data();
         (0..width*height).into_par_iter().map(move |pos| {
             let (x, y) = (pos % width, pos / width);
-            let color = Renderer::render_pixel(&self.settings, &self.scene, &self.materials, x, y, width, height, &self.camera);
+            let mut sampler = self.settings.sampler.create(x, y, frame, self.settings.seed);
+            let color = Renderer::render_pixel(&self.settings, &data, x, y, width, height, sampler.as_mut());
             color.pow(2.4)
         }).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AreaLight, BasicMaterial, Position, Sphere};
+    use interlumen_core::Vec3;
+
+    fn render() -> Vec<Color> {
+        let mut driver = RendererDriver::new();
+        driver.settings.seed = 7;
+        driver.materials.push(Box::new(BasicMaterial {
+            albedo: Color::new(0.8, 0.6, 0.4, 1.0),
+            emit: Color::BLACK,
+            roughness: 0.5,
+        }));
+        driver.scene.push(Box::new(Sphere::new(Vec3(0.0, 0.0, 3.0), 1.0, 0)));
+        driver.lights.push(Box::new(AreaLight {
+            pos: Vec3(0.0, 3.0, 2.0),
+            u: Vec3(0.5, 0.0, 0.0),
+            v: Vec3(0.0, 0.0, 0.5),
+            color: Color::WHITE,
+            intensity: 5.0,
+        }));
+        driver.init_accum_buffer(16, 16);
+        driver.append_to_accum_buffer(16, 16);
+        driver.append_to_accum_buffer(16, 16);
+        driver.accum_buffer
+    }
+
+    fn render_with_threads(threads: usize) -> Vec<Color> {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(render)
+    }
+
+    #[test]
+    fn adaptive_sampling_stops_converged_pixels() {
+        let mut driver = RendererDriver::new();
+        driver.environment = Environment::Color(Color::GRAY);
+        driver.settings.target_error = 0.01;
+        driver.settings.min_samples = 4;
+        driver.init_accum_buffer(8, 8);
+        let stats = driver.render_converged(8, 8, Duration::from_secs(5));
+        // A constant background has no variance, every pixel stops after the minimum
+        assert_eq!(stats.converged_pixels, 64);
+        assert_eq!(stats.total_samples, 64 * 4);
+        assert_eq!(driver.show_accum_buffer()[0], Color::GRAY.pow(2.4));
+    }
+
+    #[test]
+    fn resets_on_change() {
+        let mut driver = RendererDriver::new();
+        driver.init_accum_buffer(4, 4);
+        driver.append_to_accum_buffer(4, 4);
+        driver.append_to_accum_buffer(4, 4);
+        assert_eq!(driver.accum_steps, 2);
+
+        driver.camera.pos = Vec3(0.0, 1.0, 0.0);
+        driver.append_to_accum_buffer(4, 4);
+        assert_eq!(driver.accum_steps, 1);
+
+        driver.scene_mut().push(Box::new(Sphere::new(Vec3(0.0, 0.0, 3.0), 1.0, 0)));
+        driver.append_to_accum_buffer(4, 4);
+        assert_eq!(driver.accum_steps, 1);
+        assert_eq!(driver.sample_counts[0], 1);
+
+        driver.append_to_accum_buffer(2, 2);
+        assert_eq!(driver.accum_buffer.len(), 4);
+        // Same number of pixels in a different shape
+        driver.append_to_accum_buffer(2, 2);
+        driver.append_to_accum_buffer(4, 1);
+        assert_eq!(driver.accum_steps, 1);
+        assert_eq!(driver.tiles.len(), 1);
+        assert_eq!((driver.tiles[0].width, driver.tiles[0].height), (4, 1));
+        driver.init_accum_buffer(2, 2);
+        assert_eq!(driver.accum_steps, 0);
+    }
+
+    #[test]
+    fn budgeted_tiles_resume() {
+        let mut driver = RendererDriver::new();
+        driver.environment = Environment::Color(Color::WHITE);
+        driver.settings.tile_size = 4;
+        let cancel = AtomicBool::new(true);
+        let progress = driver.render_tiles(16, 16, None, &cancel);
+        assert_eq!((progress.tiles_done, progress.tiles_total), (0, 16));
+
+        let cancel = AtomicBool::new(false);
+        let progress = driver.render_tiles(16, 16, Some(Duration::ZERO), &cancel);
+        assert_eq!(progress.tiles_done, 0);
+        let progress = driver.render_tiles(16, 16, None, &cancel);
+        assert!(progress.pass_complete());
+        assert_eq!(progress.passes, 1);
+        assert!(driver.sample_counts.iter().all(|&n| n == 1));
+        // One camera ray per pixel, all of them escape
+        assert_eq!(driver.rays_traced, 256);
+    }
+
+    #[test]
+    fn pick_object() {
+        let mut driver = RendererDriver::new();
+        driver.scene.push(Box::new(Sphere::new(Vec3(-2.0, 0.0, 4.0), 1.0, 0)));
+        driver.scene.push(Box::new(Sphere::new(Vec3(2.0, 0.0, 4.0), 1.0, 1)));
+        assert_eq!(driver.pick(12, 8, 16, 16), Some(1));
+        assert_eq!(driver.pick(4, 8, 16, 16), Some(0));
+        assert_eq!(driver.pick(8, 0, 16, 16), None);
+    }
+
+    #[test]
+    fn motion_blur_smears_moving_objects() {
+        // Brightness of the pixel where the sphere starts and the one it passes through
+        let render = |shutter: f32| {
+            let mut driver = RendererDriver::new();
+            driver.environment = Environment::Color(Color::BLACK);
+            driver.settings.shutter = shutter;
+            driver.materials.push(Box::new(BasicMaterial {
+                albedo: Color::BLACK,
+                emit: Color::WHITE,
+                roughness: 1.0,
+            }));
+            let mut sphere = Sphere::new(Vec3(-2.0, 0.0, 4.0), 0.5, 0);
+            sphere.set_motion(Vec3(4.0, 0.0, 0.0));
+            driver.scene.push(Box::new(sphere));
+            driver.init_accum_buffer(16, 16);
+            for _ in 0..32 {
+                driver.append_to_accum_buffer(16, 16);
+            }
+            let row = 8 * 16;
+            (driver.accum_buffer[row + 4].r / 32.0, driver.accum_buffer[row + 8].r / 32.0)
+        };
+        assert_eq!(render(0.0), (1.0, 0.0));
+        let (start, middle) = render(0.1);
+        assert!(start < 0.5 && middle > 0.1 && middle < 0.5);
+    }
+
+    #[test]
+    fn deterministic_across_threads() {
+        let a = render_with_threads(1);
+        assert!(a.iter().any(|c| c.r > 0.0));
+        assert!(a == render_with_threads(4));
+    }
+}
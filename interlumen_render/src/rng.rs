@@ -0,0 +1,76 @@
+// Small PCG32 generator, cheap to create per pixel so every sample of every pixel
+// gets its own reproducible stream independent of how work is split between threads
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+// SplitMix64 finalizer, spreads nearby inputs over the whole state space
+pub(crate) fn hash(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(hash(seed));
+        rng.next_u32();
+        rng
+    }
+
+    // Stream for one sample of one pixel
+    pub fn for_pixel(pixel: usize, sample: usize, seed: u64) -> Self {
+        Self::new(hash(hash(seed ^ pixel as u64) ^ sample as u64))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Uniform number in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible_streams() {
+        let mut a = Rng::for_pixel(10, 3, 42);
+        let mut b = Rng::for_pixel(10, 3, 42);
+        let first = a.next_u32();
+        assert_eq!(first, b.next_u32());
+        for _ in 0..8 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+        assert_ne!(Rng::for_pixel(10, 4, 42).next_u32(), first);
+        assert_ne!(Rng::for_pixel(11, 3, 42).next_u32(), first);
+        assert_ne!(Rng::for_pixel(10, 3, 43).next_u32(), first);
+    }
+
+    #[test]
+    fn uniform_range() {
+        let mut rng = Rng::new(7);
+        let mut sum = 0.0;
+        for _ in 0..10000 {
+            let v = rng.next_f32();
+            assert!((0.0..1.0).contains(&v));
+            sum += v;
+        }
+        let mean = sum / 10000.0;
+        assert!((mean - 0.5).abs() < 0.02);
+    }
+}
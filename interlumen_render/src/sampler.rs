@@ -0,0 +1,359 @@
+use std::sync::OnceLock;
+
+use crate::rng::hash;
+use crate::Rng;
+
+// Source of the random numbers consumed along one path, dimensions are handed out in order
+pub trait Sampler {
+    fn next_1d(&mut self) -> f32;
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerKind {
+    // Sampler for the `sample`-th path through the pixel at (x, y)
+    pub fn create(&self, x: usize, y: usize, sample: usize, seed: u64) -> Box<dyn Sampler> {
+        let pixel = hash(seed ^ hash(((y as u64) << 32) | x as u64));
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler {
+                rng: Rng::for_pixel(pixel as usize, sample, seed),
+            }),
+            SamplerKind::Stratified => Box::new(StratifiedSampler {
+                rng: Rng::for_pixel(pixel as usize, sample, seed),
+                pixel,
+                sample,
+                dimension: 0,
+            }),
+            SamplerKind::Halton => Box::new(HaltonSampler {
+                pixel,
+                sample,
+                dimension: 0,
+            }),
+            SamplerKind::Sobol => Box::new(SobolSampler {
+                pixel,
+                sample: sample as u32,
+                dimension: 0,
+            }),
+            SamplerKind::BlueNoise => Box::new(BlueNoiseSampler {
+                x,
+                y,
+                seed,
+                sample,
+                dimension: 0,
+            }),
+        }
+    }
+}
+
+fn to_unit(x: u32) -> f32 {
+    (x >> 8) as f32 / (1u32 << 24) as f32
+}
+
+// Plain white noise
+pub struct IndependentSampler {
+    rng: Rng,
+}
+
+impl Sampler for IndependentSampler {
+    fn next_1d(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+}
+
+// Jittered strata over consecutive batches of `STRATA` samples, in random order per pixel
+pub struct StratifiedSampler {
+    rng: Rng,
+    pixel: u64,
+    sample: usize,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    const STRATA: usize = 16;
+    const STRATA_2D: usize = 4;
+
+    // Position of this sample inside its batch, shuffled with an affine permutation
+    fn stratum(&mut self, count: usize) -> usize {
+        let batch = (self.sample / Self::STRATA) as u64;
+        let h = hash(self.pixel ^ hash(self.dimension ^ (batch << 32)));
+        self.dimension += 1;
+        let a = (h as usize) | 1;
+        let b = (h >> 32) as usize;
+        (self.sample % Self::STRATA).wrapping_mul(a).wrapping_add(b) % count
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn next_1d(&mut self) -> f32 {
+        let stratum = self.stratum(Self::STRATA);
+        (stratum as f32 + self.rng.next_f32()) / Self::STRATA as f32
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let n = Self::STRATA_2D;
+        let stratum = self.stratum(n * n);
+        let (sx, sy) = (stratum % n, stratum / n);
+        (
+            (sx as f32 + self.rng.next_f32()) / n as f32,
+            (sy as f32 + self.rng.next_f32()) / n as f32,
+        )
+    }
+}
+
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+fn radical_inverse(base: u64, mut index: u64) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv = inv_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as f64 * inv;
+        index /= base;
+        inv *= inv_base;
+    }
+    result as f32
+}
+
+// Halton sequence with a per-pixel Cranley-Patterson rotation,
+// dimensions past the prime table fall back to hashed noise
+pub struct HaltonSampler {
+    pixel: u64,
+    sample: usize,
+    dimension: usize,
+}
+
+impl Sampler for HaltonSampler {
+    fn next_1d(&mut self) -> f32 {
+        let d = self.dimension;
+        self.dimension += 1;
+        let shift = to_unit(hash(self.pixel ^ d as u64) as u32);
+        let value = match PRIMES.get(d) {
+            Some(&base) => radical_inverse(base, self.sample as u64),
+            None => to_unit(hash(self.pixel ^ hash(((d as u64) << 32) | self.sample as u64)) as u32),
+        };
+        (value + shift).fract()
+    }
+}
+
+// Laine-Karras style hash, flips each bit based on the bits below it
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+// Owen scrambling of a binary fraction
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn sobol(index: u32, dimension: usize) -> u32 {
+    let mut result = 0;
+    let mut direction: u32 = 1;
+    for bit in 0..32 {
+        if index & (1 << bit) != 0 {
+            result ^= match dimension {
+                0 => 1 << (31 - bit),
+                _ => direction << (31 - bit),
+            };
+        }
+        direction ^= direction << 1;
+    }
+    result
+}
+
+// Owen-scrambled 2D Sobol points, every pair of dimensions gets its own shuffle and scramble
+// (Burley, "Practical Hash-based Owen Scrambling")
+pub struct SobolSampler {
+    pixel: u64,
+    sample: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    fn next_seed(&mut self) -> u32 {
+        let seed = hash(self.pixel ^ hash(self.dimension)) as u32;
+        self.dimension += 1;
+        seed
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn next_1d(&mut self) -> f32 {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.sample, seed);
+        to_unit(nested_uniform_scramble(sobol(index, 0), seed ^ 0x9e3779b9))
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let seed = self.next_seed();
+        let index = nested_uniform_scramble(self.sample, seed);
+        (
+            to_unit(nested_uniform_scramble(sobol(index, 0), seed ^ 0x9e3779b9)),
+            to_unit(nested_uniform_scramble(sobol(index, 1), seed ^ 0x7f4a7c15)),
+        )
+    }
+}
+
+const MASK_SIZE: usize = 64;
+
+// Blue-noise ranks in [0, 1) built with Ulichney's void-and-cluster method
+fn blue_noise_mask() -> &'static [f32] {
+    static MASK: OnceLock<Vec<f32>> = OnceLock::new();
+    MASK.get_or_init(|| {
+        let n = MASK_SIZE * MASK_SIZE;
+        let sigma = 1.5f32;
+        let mut kernel = vec![0.0; n];
+        for dy in 0..MASK_SIZE {
+            for dx in 0..MASK_SIZE {
+                let wx = dx.min(MASK_SIZE - dx) as f32;
+                let wy = dy.min(MASK_SIZE - dy) as f32;
+                kernel[dy * MASK_SIZE + dx] = (-(wx * wx + wy * wy) / (2.0 * sigma * sigma)).exp();
+            }
+        }
+
+        let mut pattern = vec![false; n];
+        let mut energy = vec![0.0f32; n];
+        let update = |energy: &mut [f32], p: usize, sign: f32| {
+            let (px, py) = (p % MASK_SIZE, p / MASK_SIZE);
+            for (q, e) in energy.iter_mut().enumerate() {
+                let dx = (q % MASK_SIZE + MASK_SIZE - px) % MASK_SIZE;
+                let dy = (q / MASK_SIZE + MASK_SIZE - py) % MASK_SIZE;
+                *e += sign * kernel[dy * MASK_SIZE + dx];
+            }
+        };
+        let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+            (0..n).filter(|&p| pattern[p]).max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+        let largest_void = |pattern: &[bool], energy: &[f32]| {
+            (0..n).filter(|&p| !pattern[p]).min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+
+        // Initial pattern of scattered points relaxed until it stops changing
+        let mut rng = Rng::new(0);
+        let mut ones = 0;
+        while ones < n / 10 {
+            let p = rng.next_u32() as usize % n;
+            if !pattern[p] {
+                pattern[p] = true;
+                update(&mut energy, p, 1.0);
+                ones += 1;
+            }
+        }
+        loop {
+            let cluster = tightest_cluster(&pattern, &energy).unwrap();
+            pattern[cluster] = false;
+            update(&mut energy, cluster, -1.0);
+            let void = largest_void(&pattern, &energy).unwrap();
+            pattern[void] = true;
+            update(&mut energy, void, 1.0);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut rank = vec![0; n];
+        // Ranks of the initial points, removing the tightest clusters first
+        let (mut p1, mut e1) = (pattern.clone(), energy.clone());
+        for r in (0..ones).rev() {
+            let cluster = tightest_cluster(&p1, &e1).unwrap();
+            p1[cluster] = false;
+            update(&mut e1, cluster, -1.0);
+            rank[cluster] = r;
+        }
+        // Remaining points fill the largest voids
+        for r in ones..n {
+            let void = largest_void(&pattern, &energy).unwrap();
+            pattern[void] = true;
+            update(&mut energy, void, 1.0);
+            rank[void] = r;
+        }
+        rank.iter().map(|&r| (r as f32 + 0.5) / n as f32).collect()
+    })
+}
+
+// Blue-noise mask shifted per dimension and advanced by the golden ratio every sample,
+// errors are spread as high-frequency noise across neighbouring pixels
+pub struct BlueNoiseSampler {
+    x: usize,
+    y: usize,
+    seed: u64,
+    sample: usize,
+    dimension: u64,
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn next_1d(&mut self) -> f32 {
+        let h = hash(self.seed ^ hash(self.dimension));
+        self.dimension += 1;
+        let x = (self.x + h as usize) % MASK_SIZE;
+        let y = (self.y + (h >> 32) as usize) % MASK_SIZE;
+        let value = blue_noise_mask()[y * MASK_SIZE + x];
+        (value + self.sample as f32 * 0.618_034).fract()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mean_error(kind: SamplerKind, samples: usize) -> f32 {
+        // Integrates x * y over the unit square, exact value 1/4
+        let mut error = 0.0;
+        for pixel in 0..16 {
+            let mut sum = 0.0;
+            for sample in 0..samples {
+                let (x, y) = kind.create(pixel, 0, sample, 3).next_2d();
+                assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                sum += x * y;
+            }
+            error += (sum / samples as f32 - 0.25).abs();
+        }
+        error / 16.0
+    }
+
+    #[test]
+    fn low_discrepancy_beats_white_noise() {
+        let independent = mean_error(SamplerKind::Independent, 64);
+        for kind in [SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol] {
+            assert!(mean_error(kind, 64) < independent, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn sobol_is_stratified() {
+        // Any 2^k consecutive points put exactly one sample in each 1/2^k interval
+        let mut hit = [false; 16];
+        for sample in 0..16 {
+            let x = SamplerKind::Sobol.create(5, 7, sample, 0).next_1d();
+            hit[(x * 16.0) as usize] = true;
+        }
+        assert!(hit.iter().all(|&h| h));
+    }
+
+    #[test]
+    fn blue_noise_mask_is_a_permutation() {
+        let mask = blue_noise_mask();
+        let mut ranks: Vec<usize> = mask.iter().map(|v| (v * mask.len() as f32) as usize).collect();
+        ranks.sort();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r));
+    }
+}
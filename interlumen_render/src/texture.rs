@@ -0,0 +1,126 @@
+use interlumen_core::{Color, Vec3};
+
+use crate::Image;
+
+pub trait Texture: Send + Sync {
+    fn sample(&self, uv: Vec3, point: Vec3) -> Color;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+impl WrapMode {
+    fn apply(&self, i: isize, size: usize) -> usize {
+        let size = size as isize;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Mirror => {
+                let period = i.rem_euclid(2 * size);
+                if period < size {
+                    period
+                } else {
+                    2 * size - 1 - period
+                }
+            }
+            WrapMode::Clamp => i.clamp(0, size - 1),
+        };
+        i as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    pub image: Image,
+    pub wrap: WrapMode,
+    pub filter: Filter,
+    // UV multiplier, values above 1 tile the image
+    pub scale: f32,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> Self {
+        Self {
+            image,
+            wrap: WrapMode::default(),
+            filter: Filter::default(),
+            scale: 1.0,
+        }
+    }
+
+    fn texel(&self, x: isize, y: isize) -> Color {
+        let x = self.wrap.apply(x, self.image.width);
+        let y = self.wrap.apply(y, self.image.height);
+        self.image.get(x, y)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn sample(&self, uv: Vec3, _point: Vec3) -> Color {
+        // Image rows go top to bottom while v grows upwards
+        let x = uv.0 * self.scale * self.image.width as f32;
+        let y = (1.0 - uv.1 * self.scale) * self.image.height as f32;
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as isize, y.floor() as isize),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+                let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(wrap: WrapMode, filter: Filter) -> ImageTexture {
+        let image = Image::read_ppm(b"P3\n# 2x1 black and white\n2 1\n255\n0 0 0 255 255 255\n").unwrap();
+        ImageTexture {
+            image,
+            wrap,
+            filter,
+            scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn nearest_sample() {
+        let tex = texture(WrapMode::Repeat, Filter::Nearest);
+        assert_eq!(tex.sample(Vec3(0.25, 0.5, 0.0), Vec3::default()), Color::BLACK);
+        assert_eq!(tex.sample(Vec3(0.75, 0.5, 0.0), Vec3::default()), Color::WHITE);
+        assert_eq!(tex.sample(Vec3(1.25, 0.5, 0.0), Vec3::default()), Color::BLACK);
+    }
+
+    #[test]
+    fn bilinear_sample() {
+        let tex = texture(WrapMode::Clamp, Filter::Bilinear);
+        assert_eq!(tex.sample(Vec3(0.5, 0.5, 0.0), Vec3::default()), Color::GRAY);
+        assert_eq!(tex.sample(Vec3(0.0, 0.5, 0.0), Vec3::default()), Color::BLACK);
+        assert_eq!(tex.sample(Vec3(1.0, 0.5, 0.0), Vec3::default()), Color::WHITE);
+    }
+
+    #[test]
+    fn wrap_modes() {
+        assert_eq!(WrapMode::Repeat.apply(-1, 4), 3);
+        assert_eq!(WrapMode::Mirror.apply(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.apply(5, 4), 2);
+        assert_eq!(WrapMode::Clamp.apply(9, 4), 3);
+    }
+}
@@ -0,0 +1,107 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    Scanline,
+    // Outwards from the center of the image, the interesting part usually shows up first
+    Spiral,
+    // Space-filling curve, neighbouring tiles are rendered close in time
+    #[default]
+    Hilbert,
+}
+
+// Position of the cell (x, y) along the Hilbert curve filling an `n` x `n` grid
+fn hilbert_index(n: usize, mut x: usize, mut y: usize) -> usize {
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
+
+// Splits the image into tiles of at most `size` pixels, sorted in rendering order
+pub fn tile_grid(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let (cols, rows) = (width.div_ceil(size), height.div_ceil(size));
+    let mut cells: Vec<(usize, usize)> = (0..rows).flat_map(|row| (0..cols).map(move |col| (col, row))).collect();
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            let (cx, cy) = ((cols as f32 - 1.0) / 2.0, (rows as f32 - 1.0) / 2.0);
+            let key = |&(col, row): &(usize, usize)| {
+                let (dx, dy) = (col as f32 - cx, row as f32 - cy);
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            cells.sort_by(|a, b| {
+                let (ka, kb) = (key(a), key(b));
+                ka.0.total_cmp(&kb.0).then(ka.1.total_cmp(&kb.1))
+            });
+        }
+        TileOrder::Hilbert => {
+            let n = cols.max(rows).next_power_of_two();
+            cells.sort_by_key(|&(col, row)| hilbert_index(n, col, row));
+        }
+    }
+    cells
+        .into_iter()
+        .map(|(col, row)| Tile {
+            x: col * size,
+            y: row * size,
+            width: size.min(width - col * size),
+            height: size.min(height - row * size),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_cover_image_once() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let mut covered = vec![0; 100 * 70];
+            for tile in tile_grid(100, 70, 16, order) {
+                for y in tile.y..tile.y + tile.height {
+                    for x in tile.x..tile.x + tile.width {
+                        covered[y * 100 + x] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|&c| c == 1), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn hilbert_tiles_are_adjacent() {
+        let tiles = tile_grid(128, 128, 16, TileOrder::Hilbert);
+        for pair in tiles.windows(2) {
+            let dx = pair[0].x.abs_diff(pair[1].x);
+            let dy = pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(dx + dy, 16);
+        }
+    }
+
+    #[test]
+    fn spiral_starts_in_center() {
+        let first = tile_grid(160, 160, 32, TileOrder::Spiral)[0];
+        assert_eq!((first.x, first.y), (64, 64));
+    }
+}
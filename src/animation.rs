@@ -0,0 +1,240 @@
+use std::f32::consts::PI;
+
+use interlumen_core::{Quaternion, Vec3};
+use interlumen_render::RendererDriver;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    // Holds every key until the next one
+    Step,
+    #[default]
+    Linear,
+    // Smooth curve through the keys, handles point from the previous to the next key
+    Bezier,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    ObjectPos(usize),
+    // Euler angles in degrees
+    ObjectRotation(usize),
+    CameraPos,
+    CameraDir,
+    CameraFov,
+    CameraAperture,
+    CameraFocusDist,
+    // Parameter from `Material::params`, colors are set through their .r, .g and .b parts
+    Material(usize, String),
+}
+
+// Scalar targets only use the first component of the value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    // Seconds
+    pub time: f32,
+    pub value: Vec3,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub target: Target,
+    pub interpolation: Interpolation,
+    // Loops from the last key back to the first
+    pub repeat: bool,
+    keys: Vec<Keyframe>,
+}
+
+fn bezier(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let s = 1.0 - t;
+    p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)
+}
+
+impl Track {
+    pub fn new(target: Target, interpolation: Interpolation, repeat: bool, mut keys: Vec<Keyframe>) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            target,
+            interpolation,
+            repeat,
+            keys,
+        }
+    }
+
+    pub fn end(&self) -> f32 {
+        self.keys.last().map_or(0.0, |key| key.time)
+    }
+
+    pub fn sample(&self, time: f32) -> Option<Vec3> {
+        let (first, last) = (self.keys.first()?, self.keys.last()?);
+        let length = last.time - first.time;
+        let time = if self.repeat && length > 0.0 {
+            first.time + (time - first.time).rem_euclid(length)
+        } else {
+            time
+        };
+        let i = self.keys.partition_point(|key| key.time <= time);
+        if i == 0 {
+            return Some(first.value);
+        }
+        if i == self.keys.len() {
+            return Some(last.value);
+        }
+        let (a, b) = (self.keys[i - 1], self.keys[i]);
+        let t = (time - a.time) / (b.time - a.time);
+        Some(match self.interpolation {
+            Interpolation::Step => a.value,
+            Interpolation::Linear => a.value + (b.value - a.value) * t,
+            Interpolation::Bezier => {
+                let before = if i >= 2 { self.keys[i - 2].value } else { a.value };
+                let after = self.keys.get(i + 1).map_or(b.value, |key| key.value);
+                let h1 = a.value + (b.value - before) / 6.0;
+                let h2 = b.value - (after - a.value) / 6.0;
+                bezier(a.value, h1, h2, b.value, t)
+            }
+        })
+    }
+
+    // `motion` is how much the value changes while the shutter is open, only
+    // positions and the camera direction get blurred
+    fn apply(&self, driver: &mut RendererDriver, value: Vec3, motion: Vec3) {
+        match &self.target {
+            Target::ObjectPos(index) => {
+                if let Some(obj) = driver.scene_mut().get_mut(*index) {
+                    obj.set_pos(value);
+                    obj.set_motion(motion);
+                }
+            }
+            Target::ObjectRotation(index) => {
+                if let Some(obj) = driver.scene_mut().get_mut(*index) {
+                    obj.set_rotation(Quaternion::from_euler(value * (PI / 180.0)));
+                }
+            }
+            Target::CameraPos => {
+                let camera = driver.camera_mut();
+                camera.pos = value;
+                camera.motion = motion;
+            }
+            Target::CameraDir => {
+                let camera = driver.camera_mut();
+                camera.dir = value.norm();
+                camera.dir_motion = (value + motion).norm() - value.norm();
+            }
+            Target::CameraFov => driver.camera_mut().fov = value.0,
+            Target::CameraAperture => driver.camera_mut().aperture = value.0,
+            Target::CameraFocusDist => driver.camera_mut().focus_dist = value.0,
+            Target::Material(index, name) => {
+                let Some(material) = driver.materials_mut().get_mut(*index) else {
+                    return;
+                };
+                for mut param in material.params_mut() {
+                    match param.name.strip_prefix(name.as_str()) {
+                        Some("") | Some(".r") => param.set(value.0),
+                        Some(".g") => param.set(value.1),
+                        Some(".b") => param.set(value.2),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    pub tracks: Vec<Track>,
+}
+
+impl Animation {
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    // Time of the last key in seconds
+    pub fn duration(&self) -> f32 {
+        self.tracks.iter().map(Track::end).fold(0.0, f32::max)
+    }
+
+    // Poses the scene at `time` seconds, along with the motion until the shutter closes.
+    // Tracks that were the same at `previous` are left alone, so the image can converge
+    // once an animation is over.
+    pub fn apply(&self, driver: &mut RendererDriver, time: f32, previous: Option<f32>) {
+        let shutter = driver.settings.shutter;
+        for track in &self.tracks {
+            let pose = |time: f32| Some((track.sample(time)?, track.sample(time + shutter)?));
+            if let Some((value, end)) = pose(time).filter(|&p| previous.and_then(pose) != Some(p)) {
+                track.apply(driver, value, end - value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interlumen_render::{BasicMaterial, Sphere};
+    use interlumen_core::Color;
+
+    fn keys(values: &[(f32, f32)]) -> Vec<Keyframe> {
+        values
+            .iter()
+            .map(|&(time, v)| Keyframe {
+                time,
+                value: Vec3(v, 0.0, 0.0),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interpolation() {
+        let sample = |interpolation, repeat, time| {
+            let track = Track::new(Target::CameraFov, interpolation, repeat, keys(&[(2.0, 0.0), (0.0, 0.0), (1.0, 1.0)]));
+            track.sample(time).unwrap().0
+        };
+        assert_eq!(sample(Interpolation::Linear, false, 0.5), 0.5);
+        assert_eq!(sample(Interpolation::Linear, false, -1.0), 0.0);
+        assert_eq!(sample(Interpolation::Linear, false, 1.5), 0.5);
+        assert_eq!(sample(Interpolation::Step, false, 0.9), 0.0);
+        assert_eq!(sample(Interpolation::Linear, true, 2.5), 0.5);
+        // Keys are hit exactly and the curve stays smooth around them
+        assert!((sample(Interpolation::Bezier, false, 1.0) - 1.0).abs() < 1e-6);
+        let (before, after) = (sample(Interpolation::Bezier, false, 0.99), sample(Interpolation::Bezier, false, 1.01));
+        assert!((before - after).abs() < 1e-3);
+        assert!(sample(Interpolation::Bezier, false, 0.25) < 0.25);
+    }
+
+    #[test]
+    fn animates_driver() {
+        let mut driver = RendererDriver::new();
+        driver.scene_mut().push(Box::new(Sphere::new(Vec3(0.0, 0.0, 0.0), 1.0, 0)));
+        driver.materials_mut().push(Box::new(BasicMaterial {
+            albedo: Color::BLACK,
+            emit: Color::BLACK,
+            roughness: 0.0,
+        }));
+        let vec_keys = |a: Vec3, b: Vec3| {
+            vec![Keyframe { time: 0.0, value: a }, Keyframe { time: 1.0, value: b }]
+        };
+        let animation = Animation {
+            tracks: vec![
+                Track::new(Target::ObjectPos(0), Interpolation::Linear, false, vec_keys(Vec3(0.0, 0.0, 0.0), Vec3(2.0, 0.0, 0.0))),
+                Track::new(Target::CameraFov, Interpolation::Linear, false, keys(&[(0.0, 60.0), (1.0, 90.0)])),
+                Track::new(Target::Material(0, "albedo".into()), Interpolation::Linear, false, vec_keys(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.5, 0.0))),
+                Track::new(Target::Material(0, "roughness".into()), Interpolation::Step, false, keys(&[(0.0, 0.25)])),
+            ],
+        };
+        animation.apply(&mut driver, 0.5, None);
+        assert_eq!(driver.scene()[0].pos(), Vec3(1.0, 0.0, 0.0));
+        assert_eq!(driver.camera.fov, 75.0);
+        let params: Vec<(&str, f32)> = driver.materials()[0].params().into_iter().map(|p| (p.name, p.value)).collect();
+        assert_eq!(&params[..4], [("roughness", 0.25), ("albedo.r", 0.5), ("albedo.g", 0.25), ("albedo.b", 0.0)]);
+        assert!(driver.versions.scene > 0 && driver.versions.camera > 0);
+        assert_eq!(driver.scene()[0].motion(), Vec3(0.0, 0.0, 0.0));
+
+        driver.settings.shutter = 0.25;
+        animation.apply(&mut driver, 0.5, None);
+        assert_eq!(driver.scene()[0].motion(), Vec3(0.5, 0.0, 0.0));
+        let versions = driver.versions;
+        animation.apply(&mut driver, 2.0, Some(1.5));
+        assert_eq!(driver.versions, versions);
+    }
+}
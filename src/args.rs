@@ -0,0 +1,151 @@
+use anyhow::{anyhow, bail};
+
+use crate::terminal::{ColorDepth, Output};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Console,
+    Gui,
+    // Renders the animation to image files without a window
+    Render,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub mode: Mode,
+    pub scenes: Vec<String>,
+    // Initial window size
+    pub width: usize,
+    pub height: usize,
+    // Fraction of the window resolution that is actually rendered
+    pub scale: f32,
+    // Console palette, detected from the environment when not given
+    pub colors: Option<ColorDepth>,
+    // Console output, the best one the terminal supports when not given.
+    // Render mode prints one frame with the character outputs.
+    pub output: Option<Output>,
+    // Exported time range in seconds, the end defaults to the end of the animation
+    pub start: f32,
+    pub end: Option<f32>,
+    pub fps: f32,
+    // Upper limit per frame, fewer are taken once the target error is reached or
+    // the budget in seconds runs out
+    pub samples: usize,
+    pub budget: Option<f32>,
+    // Directory for the numbered frames
+    pub out: String,
+    pub gif: Option<String>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Console,
+            scenes: Vec::new(),
+            width: 1080,
+            height: 720,
+            scale: 1.0,
+            colors: None,
+            output: None,
+            start: 0.0,
+            end: None,
+            fps: 30.0,
+            samples: 16,
+            budget: None,
+            out: "frames".to_string(),
+            gif: None,
+        }
+    }
+}
+
+fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> anyhow::Result<T> {
+    let value = value.ok_or_else(|| anyhow!("missing value for {flag}"))?;
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid value for {flag}: {value}"))
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut result = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "console" => result.mode = Mode::Console,
+                "gui" => result.mode = Mode::Gui,
+                "render" => result.mode = Mode::Render,
+                "--width" => result.width = value(&arg, args.next())?,
+                "--height" => result.height = value(&arg, args.next())?,
+                "--size" => {
+                    let size: String = value(&arg, args.next())?;
+                    let (w, h) = size
+                        .split_once('x')
+                        .ok_or_else(|| anyhow!("size should look like 1280x720"))?;
+                    result.width = value(&arg, Some(w.to_string()))?;
+                    result.height = value(&arg, Some(h.to_string()))?;
+                }
+                "--scale" => result.scale = value(&arg, args.next())?,
+                "--colors" => result.colors = Some(value(&arg, args.next())?),
+                "--output" => result.output = Some(value(&arg, args.next())?),
+                "--start" => result.start = value(&arg, args.next())?,
+                "--end" => result.end = Some(value(&arg, args.next())?),
+                "--fps" => result.fps = value(&arg, args.next())?,
+                "--samples" => result.samples = value(&arg, args.next())?,
+                "--budget" => result.budget = Some(value(&arg, args.next())?),
+                "--out" => result.out = value(&arg, args.next())?,
+                "--gif" => result.gif = Some(value(&arg, args.next())?),
+                flag if flag.starts_with("--") => bail!("unknown option {flag}"),
+                _ => result.scenes.push(arg),
+            }
+        }
+        if result.width == 0 || result.height == 0 {
+            bail!("resolution must not be zero");
+        }
+        if !(result.scale > 0.0 && result.scale <= 1.0) {
+            bail!("scale must be in (0, 1]");
+        }
+        if !result.fps.is_finite() || result.fps <= 0.0 || result.samples == 0 {
+            bail!("fps and samples must be positive");
+        }
+        if result.budget.is_some_and(|budget| budget.is_nan() || budget <= 0.0) {
+            bail!("budget must be positive");
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> anyhow::Result<Args> {
+        Args::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parse_options() {
+        let args = parse("gui --size 1920x1080 --scale 0.5 scenes/sky.toml").unwrap();
+        assert_eq!(args.mode, Mode::Gui);
+        assert_eq!((args.width, args.height, args.scale), (1920, 1080, 0.5));
+        assert_eq!(args.scenes, vec!["scenes/sky.toml"]);
+        assert_eq!(parse("--width 640").unwrap().width, 640);
+        assert_eq!(parse("--colors 256").unwrap().colors, Some(ColorDepth::Ansi256));
+        assert_eq!(parse("console --output braille").unwrap().output, Some(Output::Braille));
+        let args = parse("render --end 2 --fps 24 --samples 4 --budget 0.5 --out out --gif out.gif").unwrap();
+        assert_eq!(args.mode, Mode::Render);
+        assert_eq!((args.start, args.end, args.fps, args.samples), (0.0, Some(2.0), 24.0, 4));
+        assert_eq!(args.budget, Some(0.5));
+        assert_eq!((args.out.as_str(), args.gif.as_deref()), ("out", Some("out.gif")));
+    }
+
+    #[test]
+    fn reject_bad_options() {
+        assert!(parse("--scale 2").is_err());
+        assert!(parse("--width").is_err());
+        assert!(parse("--size 100").is_err());
+        assert!(parse("--fullscreen").is_err());
+        assert!(parse("--colors 8").is_err());
+        assert!(parse("render --fps 0").is_err());
+        assert!(parse("render --budget 0").is_err());
+    }
+}
@@ -1,58 +1,92 @@
 use std::{
-    io::stdout,
-    sync::RwLock,
+    io::{stdout, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
     time::{Duration, SystemTime},
 };
 
 use crossterm::{
-    cursor::{DisableBlinking, EnableBlinking, Hide, MoveTo, SavePosition, Show},
+    cursor::{DisableBlinking, EnableBlinking, Hide, Show},
     event::{poll, read, Event, KeyCode, KeyModifiers},
     execute,
-    style::{self, Print},
+    style::Print,
     terminal::{
         self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
     },
 };
 
-use rayon::prelude::*;
-
-use interlumen_core::{Color32, Vec3};
-use interlumen_render::{Camera, Renderer};
+use interlumen_render::RenderMode;
 
+use crate::args::Args;
 use crate::engine::Engine;
+use crate::terminal::{Capabilities, ColorDepth, Output, KITTY_CLEAR};
+use crate::worker::{Frame, RenderWorker};
 
-fn draw(width: u16, height: u16, engine: &Engine) -> anyhow::Result<()> {
-    let image = 
-    {
-        engine.renderer_driver.draw_image(width as usize, height as usize)
-    };
-    for y in 0..height as usize {
-        execute!(stdout(), SavePosition, MoveTo(0, y as u16),)?;
-        for color in &image[y*width as usize .. (y+1)*width as usize] {
-            let color = color.as_color32();
-            execute!(
-                stdout(),
-                style::SetForegroundColor(style::Color::Rgb {
-                    r: color.r,
-                    g: color.g,
-                    b: color.b
-                }),
-                Print("█")
-            )?;
-        }
-    }
+// Rendering time between published frames while accumulating
+const FRAME_BUDGET: Duration = Duration::from_millis(100);
+
+// The whole frame goes out in a single write to avoid tearing and syscall overhead
+fn draw(frame: &Frame, output: Output, cells: (usize, usize), depth: ColorDepth) -> anyhow::Result<()> {
+    let out = output.encode(&frame.pixels, frame.width, frame.height, cells, depth)?;
+    let mut stdout = stdout().lock();
+    stdout.write_all(&out)?;
+    stdout.flush()?;
     Ok(())
 }
 
-pub fn run(engine: RwLock<Engine>) -> anyhow::Result<()> {
-    let camera = Camera::unit();
-    let mut update_time = true;
+fn terminal_cells() -> anyhow::Result<(usize, usize)> {
+    let (cols, rows) = terminal::size()?;
+    Ok((cols as usize, rows as usize))
+}
+
+pub fn run(engine: Arc<RwLock<Engine>>, args: &Args) -> anyhow::Result<()> {
+    let depth = args.colors.unwrap_or_else(ColorDepth::detect);
     execute!(stdout(), EnterAlternateScreen, DisableBlinking, Hide)?;
     enable_raw_mode()?;
+    // Image outputs need the cell size in pixels, without a flag the best supported output is used
+    let caps = match args.output {
+        None | Some(Output::Sixel | Output::Kitty) => Capabilities::probe()?,
+        _ => Capabilities::default(),
+    };
+    let output = args.output.unwrap_or_else(|| caps.preferred_output());
+    let cell_pixels = caps.cell_pixels(terminal_cells()?);
     {
         let mut eng = engine.write().unwrap();
-        eng.renderer_driver.settings.pixel_ratio = 2.0;
+        eng.renderer_driver.settings.pixel_ratio = output.pixel_ratio();
     }
+    let size = output.render_size(terminal_cells()?, cell_pixels);
+    // While animations are paused the image is refined over time, otherwise
+    // every frame is a single sample of the current state
+    let paused = Arc::new(AtomicBool::new(false));
+    let worker = RenderWorker::spawn(engine.clone(), size.0, size.1, {
+        let paused = paused.clone();
+        let mut accumulating = false;
+        let mut frame = 0;
+        move |engine, width, height, cancel| {
+            if !paused.load(Ordering::Relaxed) {
+                accumulating = false;
+                frame += 1;
+                let eng = engine.read().unwrap();
+                return Some(Frame::new(width, height, eng.renderer_driver.draw_image(width, height, frame)));
+            }
+            let mut eng = engine.write().unwrap();
+            let driver = &mut eng.renderer_driver;
+            // The scene may have moved since the last pause, start over.
+            // Resizes are picked up by the driver itself.
+            if !accumulating {
+                driver.init_accum_buffer(width, height);
+                accumulating = true;
+            }
+            let progress = driver.render_tiles(width, height, Some(FRAME_BUDGET), cancel);
+            Some(Frame {
+                progress,
+                samples: driver.accum_steps,
+                ..Frame::new(width, height, driver.show_accum_buffer())
+            })
+        }
+    });
     loop {
         if poll(Duration::from_millis(1))? {
             match read()? {
@@ -65,29 +99,35 @@ pub fn run(engine: RwLock<Engine>) -> anyhow::Result<()> {
                     break
                 }
                 Event::Key(event) if event.code == KeyCode::Char('p') => {
-                    update_time = !update_time;
+                    paused.fetch_xor(true, Ordering::Relaxed);
                     let mut eng = engine.write().unwrap();
                     eng.last_frame = SystemTime::now();
                 }
+                // Number keys pick the render mode
+                Event::Key(event) => {
+                    if let KeyCode::Char(c @ '1'..='7') = event.code {
+                        let mode = RenderMode::ALL[c as usize - '1' as usize];
+                        engine.write().unwrap().renderer_driver.settings_mut().render_mode = mode;
+                    }
+                }
                 _ => {}
             }
         } else {
             // Update engine state
-            {
-                let mut eng = engine.write().unwrap();
-                eng.next_frame();
-                let time = eng.time;
-                if let Some(obj) = eng.scene.get_mut(0) {
-                    obj.set_pos(Vec3((time * 0.001).sin() * 2.0, 0.0, 3.0));
-                }
+            if !paused.load(Ordering::Relaxed) {
+                engine.write().unwrap().next_frame();
             }
-            {
-                let size = terminal::size()?;
-                let eng = engine.read().unwrap();
-                draw(size.0, size.1, &eng)?;
+            let cells = terminal_cells()?;
+            let size = output.render_size(cells, cell_pixels);
+            worker.resize(size.0, size.1);
+            if let Some(frame) = worker.take_frame() {
+                draw(&frame, output, cells, depth)?;
             }
         }
     }
+    if output == Output::Kitty {
+        execute!(stdout(), Print(KITTY_CLEAR))?;
+    }
     execute!(stdout(), LeaveAlternateScreen, EnableBlinking, Show)?;
     disable_raw_mode()?;
     Ok(())
@@ -0,0 +1,97 @@
+use interlumen_core::Vec3;
+use interlumen_render::Camera;
+
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    // Free movement, looking around from the camera position
+    Fly,
+    // Looking at a fixed target, rotating moves the camera around it
+    Orbit,
+}
+
+pub struct CameraController {
+    pub mode: CameraMode,
+    // Units per second
+    pub speed: f32,
+    // Radians per pixel of mouse movement
+    pub sensitivity: f32,
+    yaw: f32,
+    pitch: f32,
+    target: Vec3,
+    distance: f32,
+}
+
+fn forward(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos())
+}
+
+impl CameraController {
+    pub fn new(camera: &Camera) -> Self {
+        let dir = camera.dir.norm();
+        Self {
+            mode: CameraMode::Fly,
+            speed: 2.0,
+            sensitivity: 0.005,
+            yaw: dir.0.atan2(dir.2),
+            pitch: dir.1.clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH),
+            target: camera.pos + dir * camera.focus_dist,
+            distance: camera.focus_dist,
+        }
+    }
+
+    // Orbiting happens around the point in focus
+    pub fn set_mode(&mut self, camera: &Camera, mode: CameraMode) {
+        self.mode = mode;
+        self.distance = camera.focus_dist.max(0.1);
+        self.target = camera.pos + camera.dir.norm() * self.distance;
+    }
+
+    // Moves along the camera axes, `amount` is (right, up, forward)
+    pub fn translate(&mut self, camera: &mut Camera, amount: Vec3) {
+        let (right, _, forward) = camera.basis();
+        let offset = right * amount.0 + Vec3(0.0, amount.1, 0.0) + forward * amount.2;
+        camera.pos += offset;
+        self.target += offset;
+    }
+
+    pub fn rotate(&mut self, camera: &mut Camera, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+        camera.dir = forward(self.yaw, self.pitch);
+        if self.mode == CameraMode::Orbit {
+            camera.pos = self.target - camera.dir * self.distance;
+        }
+    }
+
+    pub fn zoom(&mut self, camera: &mut Camera, amount: f32) {
+        camera.fov = (camera.fov - amount).clamp(5.0, 170.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orbit_keeps_distance() {
+        let mut camera = Camera::new(Vec3(0.0, 0.0, -2.0), Vec3(0.0, 0.0, 1.0), 90.0);
+        camera.focus_dist = 2.0;
+        let mut controller = CameraController::new(&camera);
+        controller.set_mode(&camera, CameraMode::Orbit);
+        controller.rotate(&mut camera, 1.0, 0.3);
+        assert!((camera.pos.len() - 2.0).abs() < 1e-4);
+        assert!((camera.pos + camera.dir * 2.0).len() < 1e-4);
+    }
+
+    #[test]
+    fn fly_moves_along_view() {
+        let mut camera = Camera::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), 90.0);
+        let mut controller = CameraController::new(&camera);
+        controller.rotate(&mut camera, 0.0, 0.0);
+        assert!((camera.dir - Vec3(1.0, 0.0, 0.0)).len() < 1e-5);
+        controller.translate(&mut camera, Vec3(0.0, 0.0, 1.0));
+        assert!((camera.pos - Vec3(1.0, 0.0, 0.0)).len() < 1e-5);
+    }
+}
@@ -1,10 +1,13 @@
 use std::time::SystemTime;
 
-use interlumen_render::{RendererDriver, Scene};
+use interlumen_render::RendererDriver;
+
+use crate::animation::Animation;
 
 pub struct Engine {
-    pub scene: Scene,
     pub renderer_driver: RendererDriver,
+    pub animation: Animation,
+    // Milliseconds
     pub time: f32,
     pub last_frame: SystemTime,
 }
@@ -13,17 +16,34 @@ impl Engine {
     pub fn new() -> Self {
         Self {
             renderer_driver: RendererDriver::new(),
-            scene: Scene::new(),
+            animation: Animation::default(),
             time: 0.0,
             last_frame: SystemTime::now(),
         }
     }
 
     pub fn next_frame(&mut self) {
-        self.time += SystemTime::now()
-            .duration_since(self.last_frame)
-            .expect("Time went backwards")
-            .as_millis() as f32;
-        self.last_frame = SystemTime::now();
+        // Called every millisecond or two, so whole milliseconds would lose most of the time
+        let now = SystemTime::now();
+        let elapsed = now.duration_since(self.last_frame).expect("Time went backwards").as_secs_f32() * 1000.0;
+        self.last_frame = now;
+        self.advance(elapsed);
+    }
+
+    // Moves the clock forward by `dt` milliseconds independent of the wall clock
+    pub fn advance(&mut self, dt: f32) {
+        self.seek(self.time + dt);
+    }
+
+    // Sets the clock to `time` milliseconds, only tracks that changed since the old time are applied
+    pub fn seek(&mut self, time: f32) {
+        let previous = self.time;
+        self.time = time;
+        self.animation.apply(&mut self.renderer_driver, self.time * 0.001, Some(previous * 0.001));
+    }
+
+    // Poses the whole scene at the current time
+    pub fn animate(&mut self) {
+        self.animation.apply(&mut self.renderer_driver, self.time * 0.001, None);
     }
 }
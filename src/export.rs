@@ -0,0 +1,195 @@
+use std::{
+    fs::{self, File},
+    io::{stdout, Write},
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::bail;
+use gif::{Encoder, Repeat};
+use interlumen_core::Color;
+use interlumen_render::{ConvergenceStats, Image, RendererDriver};
+
+use crate::args::Args;
+use crate::engine::Engine;
+use crate::terminal::{self, Output};
+
+// Looping animations leave out the end so the same pose isn't shown twice,
+// otherwise the final pose gets a frame too
+fn frame_count(start: f32, end: f32, fps: f32, looping: bool) -> usize {
+    let frames = ((end - start) * fps).round().max(0.0) as usize;
+    if looping {
+        frames.max(1)
+    } else {
+        frames + 1
+    }
+}
+
+// Takes up to `--samples` samples per pixel, stopping early once every pixel reaches the
+// target error or `--budget` seconds have passed
+fn render(driver: &mut RendererDriver, width: usize, height: usize, args: &Args) -> (Vec<Color>, ConvergenceStats) {
+    let start = Instant::now();
+    driver.init_accum_buffer(width, height);
+    let mut stats = driver.convergence();
+    for _ in 0..args.samples {
+        driver.append_to_accum_buffer(width, height);
+        stats = driver.convergence();
+        let out_of_time = args.budget.is_some_and(|budget| start.elapsed().as_secs_f32() >= budget);
+        if stats.converged_pixels == stats.total_pixels || out_of_time {
+            break;
+        }
+    }
+    (driver.show_accum_buffer(), stats)
+}
+
+// Character outputs print the frame at `--start` to stdout instead, the size is
+// taken as a terminal that many pixels large
+fn print_frame(engine: &mut Engine, args: &Args, output: Output, out: &mut impl Write) -> anyhow::Result<()> {
+    let (width, height) = output.render_size(terminal::default_cells(args.width, args.height), (1, 1));
+    engine.time = args.start * 1000.0;
+    engine.animate();
+    let driver = &mut engine.renderer_driver;
+    driver.settings.pixel_ratio = output.pixel_ratio();
+    let (pixels, _) = render(driver, width, height, args);
+    let lines = match output {
+        Output::Ascii => terminal::ascii_lines(&pixels, width, height),
+        _ => terminal::braille_lines(&pixels, width, height),
+    };
+    for line in lines {
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+// Renders every frame at its exact time on the animation clock rather than the
+// wall clock, so the output doesn't depend on render speed
+pub fn run(engine: &mut Engine, args: &Args) -> anyhow::Result<()> {
+    match args.output {
+        None => {}
+        Some(output @ (Output::Ascii | Output::Braille)) => {
+            return print_frame(engine, args, output, &mut stdout().lock());
+        }
+        Some(_) => bail!("render mode only prints ascii or braille"),
+    }
+    let (width, height) = (args.width, args.height);
+    let end = args.end.unwrap_or_else(|| engine.animation.duration());
+    let looping = engine.animation.tracks.iter().any(|track| track.repeat);
+    let frames = frame_count(args.start, end, args.fps, looping);
+    fs::create_dir_all(&args.out)?;
+    let mut gif = match &args.gif {
+        Some(_) if width > u16::MAX as usize || height > u16::MAX as usize => {
+            bail!("{width}x{height} is too large for a GIF")
+        }
+        Some(path) => {
+            let mut encoder = Encoder::new(File::create(path)?, width as u16, height as u16, &[])?;
+            encoder.set_repeat(Repeat::Infinite)?;
+            Some(encoder)
+        }
+        None => None,
+    };
+    // Hundredths of a second
+    let delay = (100.0 / args.fps).round() as u16;
+
+    engine.time = args.start * 1000.0;
+    engine.animate();
+    for i in 0..frames {
+        // Summing frame lengths would drift away from the last key
+        engine.seek(args.start * 1000.0 + i as f32 * 1000.0 / args.fps);
+        let (pixels, stats) = render(&mut engine.renderer_driver, width, height, args);
+        let image = Image { width, height, pixels };
+        let path = Path::new(&args.out).join(format!("frame_{i:04}.png"));
+        image.save_png(&path)?;
+        if let Some(encoder) = &mut gif {
+            let mut frame = gif::Frame::from_rgb_speed(width as u16, height as u16, &image.to_rgb8(), 10);
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+        let mut progress = format!("{} ({}/{frames}, {:.2} s", path.display(), i + 1, engine.time * 0.001);
+        if engine.renderer_driver.settings.target_error > 0.0 {
+            progress += &format!(", {:.1}% converged", stats.converged_fraction() * 100.0);
+        }
+        println!("{progress})");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{Animation, Interpolation, Keyframe, Target, Track};
+    use interlumen_core::Vec3;
+    use interlumen_render::Environment;
+
+    #[test]
+    fn counts_frames() {
+        assert_eq!(frame_count(0.0, 2.0, 30.0, true), 60);
+        assert_eq!(frame_count(0.0, 2.0, 30.0, false), 61);
+        assert_eq!(frame_count(1.0, 1.0, 30.0, true), 1);
+        assert_eq!(frame_count(1.0, 1.0, 30.0, false), 1);
+        assert_eq!(frame_count(0.0, 0.1, 24.0, true), 2);
+    }
+
+    #[test]
+    fn writes_frames() {
+        let dir = std::env::temp_dir().join(format!("interlumen_export_{}", std::process::id()));
+        let args = Args {
+            width: 4,
+            height: 3,
+            fps: 30.0,
+            samples: 1,
+            out: dir.to_string_lossy().into_owned(),
+            gif: Some(dir.join("preview.gif").to_string_lossy().into_owned()),
+            ..Args::default()
+        };
+        let mut engine = Engine::new();
+        let keys = vec![
+            Keyframe { time: 0.0, value: Vec3(90.0, 0.0, 0.0) },
+            Keyframe { time: 1.5, value: Vec3(60.0, 0.0, 0.0) },
+        ];
+        engine.animation = Animation {
+            tracks: vec![Track::new(Target::CameraFov, Interpolation::Linear, false, keys)],
+        };
+        run(&mut engine, &args).unwrap();
+        // 45 frame lengths after the first frame, ending exactly on the last key
+        assert_eq!(engine.time, 1500.0);
+        assert_eq!(engine.renderer_driver.camera.fov, 60.0);
+        let frame = Image::load_png(dir.join("frame_0045.png")).unwrap();
+        assert_eq!((frame.width, frame.height), (4, 3));
+        assert!(!dir.join("frame_0046.png").exists());
+        assert!(dir.join("preview.gif").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stops_at_target_error() {
+        let mut driver = RendererDriver::new();
+        *driver.environment_mut() = Environment::Color(Color::GRAY);
+        driver.settings.target_error = 0.01;
+        driver.settings.min_samples = 4;
+        let args = Args { samples: 64, ..Args::default() };
+        // A constant background converges after the minimum number of samples
+        let (_, stats) = render(&mut driver, 4, 4, &args);
+        assert_eq!(stats.converged_pixels, 16);
+        assert_eq!(stats.total_samples, 16 * 4);
+    }
+
+    #[test]
+    fn prints_text() {
+        let args = Args {
+            width: 160,
+            height: 64,
+            samples: 1,
+            ..Args::default()
+        };
+        // 20x4 cells either way, braille packs 2x4 pixels into each of them
+        for output in [Output::Ascii, Output::Braille] {
+            let mut out = Vec::new();
+            print_frame(&mut Engine::new(), &args, output, &mut out).unwrap();
+            let text = String::from_utf8(out).unwrap();
+            assert_eq!(text.lines().count(), 4);
+            assert!(text.lines().all(|line| line.chars().count() == 20));
+            assert!(!text.contains('\x1b'));
+        }
+        assert!(run(&mut Engine::new(), &Args { output: Some(Output::Sixel), ..Args::default() }).is_err());
+    }
+}
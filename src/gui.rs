@@ -1,57 +1,279 @@
-use std::{sync::RwLock, time::SystemTime};
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
 
 use interlumen_core::Vec3;
-use interlumen_render::{Camera, Renderer};
-
-use minifb::{Key, Window, WindowOptions};
+use interlumen_render::{Camera, RenderMode};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, ScaleMode, Window, WindowOptions};
 use rayon::prelude::*;
 
+use crate::args::Args;
+use crate::controls::{CameraController, CameraMode};
 use crate::engine::Engine;
+use crate::hud::Hud;
+use crate::inspector::{Edit, Inspector};
+use crate::worker::{Frame, RenderWorker};
+
+// Rendering time between published frames
+const FRAME_BUDGET: Duration = Duration::from_millis(30);
+
+// Size of the rendered image for a window of the given size
+fn render_size((width, height): (usize, usize), scale: f32) -> (usize, usize) {
+    let scaled = |n: usize| ((n as f32 * scale) as usize).max(1);
+    (scaled(width), scaled(height))
+}
+
+// Applies keyboard and mouse input to the camera
+fn update_camera(
+    window: &Window,
+    controller: &mut CameraController,
+    camera: &mut Camera,
+    last_mouse: &mut Option<(f32, f32)>,
+    dt: f32,
+) {
+    if window.is_key_pressed(Key::O, KeyRepeat::No) {
+        let mode = match controller.mode {
+            CameraMode::Fly => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Fly,
+        };
+        controller.set_mode(camera, mode);
+    }
+
+    let axis = |pos: Key, neg: Key| window.is_key_down(pos) as i32 as f32 - window.is_key_down(neg) as i32 as f32;
+    let movement = Vec3(axis(Key::D, Key::A), axis(Key::E, Key::Q), axis(Key::W, Key::S));
+    if movement != Vec3(0.0, 0.0, 0.0) {
+        let boost = if window.is_key_down(Key::LeftShift) { 4.0 } else { 1.0 };
+        controller.translate(camera, movement * (controller.speed * boost * dt));
+    }
 
-const WIDTH: usize = 1080;
-const HEIGHT: usize = 720;
+    // Dragging with the right mouse button looks around
+    let mouse = window.get_mouse_pos(MouseMode::Pass);
+    if window.get_mouse_down(MouseButton::Right) {
+        if let (Some((x, y)), Some((last_x, last_y))) = (mouse, *last_mouse) {
+            if (x, y) != (last_x, last_y) {
+                let sensitivity = controller.sensitivity;
+                controller.rotate(camera, (x - last_x) * sensitivity, -(y - last_y) * sensitivity);
+            }
+        }
+    }
+    *last_mouse = mouse;
 
-pub fn run(engine: RwLock<Engine>) -> anyhow::Result<()> {
-    let mut buffer: Vec<u32> = vec![0; WIDTH * HEIGHT];
+    if let Some((_, scroll)) = window.get_scroll_wheel() {
+        controller.zoom(camera, scroll);
+    }
+
+    if window.is_key_pressed(Key::RightBracket, KeyRepeat::Yes) {
+        camera.aperture = (camera.aperture * 1.25).max(0.01);
+    }
+    if window.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) {
+        camera.aperture = if camera.aperture > 0.01 { camera.aperture / 1.25 } else { 0.0 };
+    }
+    if window.is_key_pressed(Key::Period, KeyRepeat::Yes) {
+        camera.focus_dist *= 1.1;
+    }
+    if window.is_key_pressed(Key::Comma, KeyRepeat::Yes) {
+        camera.focus_dist = (camera.focus_dist / 1.1).max(0.05);
+    }
+}
+
+// Keys editing the selected object
+fn inspector_edits(window: &Window) -> Vec<Edit> {
+    const STEP: f32 = 0.05;
+    let bindings = [
+        (Key::Left, Edit::Move(Vec3(-STEP, 0.0, 0.0))),
+        (Key::Right, Edit::Move(Vec3(STEP, 0.0, 0.0))),
+        (Key::Down, Edit::Move(Vec3(0.0, 0.0, -STEP))),
+        (Key::Up, Edit::Move(Vec3(0.0, 0.0, STEP))),
+        (Key::PageDown, Edit::Move(Vec3(0.0, -STEP, 0.0))),
+        (Key::PageUp, Edit::Move(Vec3(0.0, STEP, 0.0))),
+        (Key::F, Edit::Scale(1.0 / 1.05)),
+        (Key::R, Edit::Scale(1.05)),
+        (Key::V, Edit::Material(-1)),
+        (Key::B, Edit::Material(1)),
+        (Key::Tab, Edit::NextParam),
+        (Key::N, Edit::Param(-STEP)),
+        (Key::M, Edit::Param(STEP)),
+    ];
+    bindings
+        .into_iter()
+        .filter(|(key, _)| window.is_key_pressed(*key, KeyRepeat::Yes))
+        .map(|(_, edit)| edit)
+        .collect()
+}
 
-    let mut window = Window::new("Interlumen", WIDTH, HEIGHT, WindowOptions::default())?;
+pub fn run(engine: Arc<RwLock<Engine>>, args: &Args) -> anyhow::Result<()> {
+    let mut window = Window::new(
+        "Interlumen",
+        args.width,
+        args.height,
+        WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::Stretch,
+            ..WindowOptions::default()
+        },
+    )?;
 
     // Limit to max ~60 fps update rate
     window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
-    {
+
+    // The rendered image gets stretched over the whole window, a smaller
+    // scale keeps things interactive on large or high-DPI windows
+    let mut scale = args.scale;
+    let mut size = render_size(window.get_size(), scale);
+    let mut buffer: Vec<u32> = vec![0; size.0 * size.1];
+
+    let worker = RenderWorker::spawn(engine.clone(), size.0, size.1, |engine, width, height, cancel| {
         let mut eng = engine.write().unwrap();
-        eng.renderer_driver.init_accum_buffer(WIDTH, HEIGHT);
-    }
+        let driver = &mut eng.renderer_driver;
+        let progress = driver.render_tiles(width, height, Some(FRAME_BUDGET), cancel);
+        let stats = (driver.settings.target_error > 0.0).then(|| driver.convergence());
+        Some(Frame {
+            progress,
+            stats,
+            samples: driver.accum_steps,
+            rays_traced: driver.rays_traced,
+            settings: Some(driver.settings.clone()),
+            ..Frame::new(width, height, driver.show_accum_buffer())
+        })
+    });
+
+    // The UI owns the camera, the engine only gets a copy when it changes
+    let mut camera = engine.read().unwrap().renderer_driver.camera.clone();
+    let mut controller = CameraController::new(&camera);
+    let mut last_mouse = None;
+    let mut last_update = Instant::now();
+
+    let mut inspector = Inspector::default();
+    let mut selection = None;
+    let mut was_clicked = false;
+
+    let mut hud = Hud::new();
+    // Last presented frame without its pixels, and the window sized buffer the HUD is drawn on
+    let mut last_frame: Option<Frame> = None;
+    let mut display: Vec<u32> = Vec::new();
 
     let mut update_time = true;
-    while window.is_open() && !window.is_key_down(Key::Q) {
+    let mut pass_done = true;
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let dt = last_update.elapsed().as_secs_f32();
+        last_update = Instant::now();
+
         if window.is_key_released(Key::P) {
             update_time = !update_time;
             let mut eng = engine.write().unwrap();
             eng.last_frame = SystemTime::now();
         }
-        // Update engine state
-        {
-            // let mut eng = engine.write().unwrap();
-            // eng.next_frame();
-            // let time = eng.time;
-            // if let Some(obj) = eng.scene.get_mut(0) {
-            //     obj.set_pos(Vec3((time * 0.001).sin() * 2.0, 0.0, 3.0));
-            // }
-        }
-        // Render
-        {
+        let mode_keys = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7];
+        for (key, mode) in mode_keys.into_iter().zip(RenderMode::ALL) {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                engine.write().unwrap().renderer_driver.settings_mut().render_mode = mode;
+            }
+        }
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            hud.visible = !hud.visible;
+        }
+        if window.is_key_pressed(Key::Minus, KeyRepeat::No) {
+            scale = (scale * 0.5).max(0.125);
+        }
+        if window.is_key_pressed(Key::Equal, KeyRepeat::No) {
+            scale = (scale * 2.0).min(1.0);
+        }
+        let previous = camera.clone();
+        update_camera(&window, &mut controller, &mut camera, &mut last_mouse, dt);
+        if camera != previous {
+            *engine.write().unwrap().renderer_driver.camera_mut() = camera.clone();
+        }
+        // Clicking selects the object under the cursor
+        let clicked = window.get_mouse_down(MouseButton::Left);
+        let previous_selection = inspector.selected;
+        if clicked && !was_clicked {
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                let (win_w, win_h) = window.get_size();
+                let x = (x / win_w as f32 * size.0 as f32) as usize;
+                let y = (y / win_h as f32 * size.1 as f32) as usize;
+                let eng = engine.read().unwrap();
+                inspector.select(eng.renderer_driver.pick(x, y, size.0, size.1));
+            }
+        }
+        was_clicked = clicked;
+        if window.is_key_pressed(Key::Backspace, KeyRepeat::No) {
+            inspector.select(None);
+        }
+        let edits = inspector_edits(&window);
+        if inspector.selected != previous_selection || (inspector.selected.is_some() && !edits.is_empty()) {
             let mut eng = engine.write().unwrap();
-            eng.renderer_driver.append_to_accum_buffer(WIDTH, HEIGHT);
+            for edit in edits {
+                inspector.apply(&mut eng.renderer_driver, edit);
+            }
+            selection = inspector.describe(&eng.renderer_driver);
+        }
+        // Follow window resizes, the driver reallocates its buffers on the next pass
+        let new_size = render_size(window.get_size(), scale);
+        if new_size != size {
+            size = new_size;
+            worker.resize(size.0, size.1);
         }
-        {
-            let eng = engine.read().unwrap();
-            buffer.par_iter_mut().zip(eng.renderer_driver.show_accum_buffer().into_par_iter()).for_each(move |(i, color)| {
+        // Animations advance once the previous pose finished a pass, so half rendered
+        // passes never show up
+        if update_time && pass_done && !engine.read().unwrap().animation.is_empty() {
+            let mut eng = engine.write().unwrap();
+            eng.next_frame();
+            pass_done = false;
+            // Animated cameras take over from the controls
+            if eng.renderer_driver.camera != camera {
+                camera = eng.renderer_driver.camera.clone();
+                let mode = controller.mode;
+                controller = CameraController::new(&camera);
+                controller.set_mode(&camera, mode);
+            }
+        }
+        // Present the latest frame from the render thread
+        // Frames rendered before a resize are dropped
+        if let Some(mut frame) = worker.take_frame().filter(|f| (f.width, f.height) == size) {
+            hud.frame_rendered(&frame);
+            let progress = frame.progress;
+            pass_done |= progress.pass_complete();
+            let mut title = format!(
+                "Interlumen - {}x{}, pass {}, {:.0}%",
+                size.0,
+                size.1,
+                progress.passes + 1,
+                progress.fraction() * 100.0
+            );
+            if let Some(stats) = frame.stats {
+                title += &format!(", {:.1}% converged", stats.converged_fraction() * 100.0);
+            }
+            if let Some(selection) = &selection {
+                title += &format!(" | {selection}");
+            }
+            window.set_title(&title);
+            buffer.resize(size.0 * size.1, 0);
+            let pixels = std::mem::take(&mut frame.pixels);
+            buffer.par_iter_mut().zip(pixels.into_par_iter()).for_each(move |(i, color)| {
                 let rgb = color.as_color32();
                 *i = (rgb.b as u32) | (rgb.g as u32) << 8 | (rgb.r as u32) << 16;
             });
+            last_frame = Some(frame);
+        }
+        let (win_w, win_h) = window.get_size();
+        if buffer.len() != size.0 * size.1 {
+            window.update();
+        } else if let (Some(frame), true, false) = (&last_frame, hud.visible, win_w * win_h == 0) {
+            // The HUD is drawn at window resolution so the text stays sharp at any render scale
+            display.resize(win_w * win_h, 0);
+            display.par_chunks_mut(win_w).enumerate().for_each(|(y, row)| {
+                let src = &buffer[y * size.1 / win_h * size.0..][..size.0];
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    *pixel = src[x * size.0 / win_w];
+                }
+            });
+            let lines = hud.lines(frame, scale, &camera, selection.as_deref());
+            hud.draw(&mut display, win_w, 2, &lines);
+            window.update_with_buffer(&display, win_w, win_h)?;
+        } else {
+            window.update_with_buffer(&buffer, size.0, size.1)?;
         }
-        window.update_with_buffer(&buffer, WIDTH, HEIGHT)?;
     }
     Ok(())
 }
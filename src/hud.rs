@@ -0,0 +1,214 @@
+use std::time::Instant;
+
+use interlumen_render::Camera;
+
+use crate::worker::Frame;
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+// 3x5 pixel font, one row per byte with the leftmost pixel in the highest bit.
+// Lowercase letters are drawn as uppercase.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0; GLYPH_HEIGHT],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '|' => [0b010, 0b010, 0b010, 0b010, 0b010],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+// Draws text with its top left corner at (x, y), every font pixel becomes a `scale` sized square
+pub fn draw_text(buffer: &mut [u32], width: usize, x: usize, y: usize, scale: usize, color: u32, text: &str) {
+    let height = buffer.len() / width.max(1);
+    for (i, c) in text.chars().enumerate() {
+        let gx = x + i * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in glyph(c).into_iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for py in y + row * scale..(y + (row + 1) * scale).min(height) {
+                    for px in gx + col * scale..(gx + (col + 1) * scale).min(width) {
+                        buffer[py * width + px] = color;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Halves the brightness of a rectangle so text on top of it stays readable
+fn darken(buffer: &mut [u32], width: usize, x: usize, y: usize, w: usize, h: usize) {
+    let height = buffer.len() / width.max(1);
+    for py in y..(y + h).min(height) {
+        for px in x..(x + w).min(width) {
+            let pixel = &mut buffer[py * width + px];
+            *pixel = (*pixel >> 1) & 0x7f7f7f;
+        }
+    }
+}
+
+fn si(value: f64) -> String {
+    match value {
+        v if v >= 1e9 => format!("{:.2}G", v / 1e9),
+        v if v >= 1e6 => format!("{:.2}M", v / 1e6),
+        v if v >= 1e3 => format!("{:.1}K", v / 1e3),
+        v => format!("{v:.0}"),
+    }
+}
+
+// Statistics overlay, smooths frame times and ray throughput over a few frames
+pub struct Hud {
+    pub visible: bool,
+    frame_time: f32,
+    rays_per_sec: f64,
+    last_frame: Option<(Instant, u64)>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self {
+            visible: true,
+            frame_time: 0.0,
+            rays_per_sec: 0.0,
+            last_frame: None,
+        }
+    }
+
+    // Frame times are measured between rendered frames, the window redraws more often
+    pub fn frame_rendered(&mut self, frame: &Frame) {
+        let now = Instant::now();
+        if let Some((time, rays)) = self.last_frame {
+            let elapsed = now.duration_since(time).as_secs_f64();
+            self.frame_time += (elapsed as f32 - self.frame_time) * 0.1;
+            if elapsed > 0.0 {
+                let rate = frame.rays_traced.saturating_sub(rays) as f64 / elapsed;
+                self.rays_per_sec += (rate - self.rays_per_sec) * 0.2;
+            }
+        }
+        self.last_frame = Some((now, frame.rays_traced));
+    }
+
+    pub fn lines(&self, frame: &Frame, scale: f32, camera: &Camera, selection: Option<&str>) -> Vec<String> {
+        let mut lines = vec![
+            format!("FRAME {:.1} MS", self.frame_time * 1000.0),
+            format!("RESOLUTION {}X{} ({:.0}%)", frame.width, frame.height, scale * 100.0),
+            format!(
+                "SAMPLES {} PASS {:.0}%",
+                frame.samples,
+                frame.progress.fraction() * 100.0
+            ),
+            format!("RAYS/S {}", si(self.rays_per_sec)),
+        ];
+        if let Some(stats) = frame.stats {
+            lines.push(format!("CONVERGED {:.1}%", stats.converged_fraction() * 100.0));
+        }
+        if let Some(settings) = &frame.settings {
+            lines.push(format!("MODE {}", settings.render_mode.name()));
+            lines.push(format!(
+                "MAX_ITER {} RAY_DEPTH {} HIT_THRES {}",
+                settings.max_iter, settings.ray_depth, settings.hit_thres
+            ));
+        }
+        let pos = camera.pos;
+        lines.push(format!(
+            "CAMERA {:.2} {:.2} {:.2} FOV {:.0}",
+            pos.0, pos.1, pos.2, camera.fov
+        ));
+        if let Some(selection) = selection {
+            lines.push(selection.to_string());
+        }
+        lines
+    }
+
+    pub fn draw(&self, buffer: &mut [u32], width: usize, scale: usize, lines: &[String]) {
+        let (margin, line_height) = (2 * scale, (GLYPH_HEIGHT + 2) * scale);
+        let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let w = columns * (GLYPH_WIDTH + 1) * scale + 2 * margin;
+        darken(buffer, width, 0, 0, w, lines.len() * line_height + 2 * margin);
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(buffer, width, margin, margin + i * line_height, scale, 0xffffff, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_glyphs() {
+        let mut buffer = vec![0; 8 * 5];
+        draw_text(&mut buffer, 8, 0, 0, 1, 1, "1L");
+        let rows: Vec<String> = buffer
+            .chunks(8)
+            .map(|row| row.iter().map(|&p| if p == 1 { '#' } else { '.' }).collect())
+            .collect();
+        assert_eq!(rows, [".#..#...", "##..#...", ".#..#...", ".#..#...", "###.###."]);
+    }
+
+    #[test]
+    fn times_rendered_frames() {
+        let mut hud = Hud::new();
+        let frame = Frame::new(1, 1, vec![Default::default()]);
+        hud.frame_rendered(&frame);
+        assert_eq!(hud.frame_time, 0.0);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        hud.frame_rendered(&frame);
+        assert!(hud.frame_time >= 0.002);
+    }
+
+    #[test]
+    fn clips_to_buffer() {
+        let mut buffer = vec![0; 4 * 4];
+        draw_text(&mut buffer, 4, 2, 2, 2, 1, "88");
+        assert_eq!(buffer.iter().filter(|&&p| p == 1).count(), 4);
+    }
+}
@@ -0,0 +1,148 @@
+use interlumen_core::Vec3;
+use interlumen_render::RendererDriver;
+
+pub enum Edit {
+    Move(Vec3),
+    // Multiplies the radius
+    Scale(f32),
+    // Cycles through the materials of the scene
+    Material(isize),
+    // Cycles through the parameters of the material
+    NextParam,
+    Param(f32),
+}
+
+// Selected object and the material parameter being edited
+#[derive(Default)]
+pub struct Inspector {
+    pub selected: Option<usize>,
+    param: usize,
+}
+
+impl Inspector {
+    pub fn select(&mut self, object: Option<usize>) {
+        self.selected = object;
+        self.param = 0;
+    }
+
+    // Every edit goes through the version-bumping accessors so the image restarts
+    pub fn apply(&mut self, driver: &mut RendererDriver, edit: Edit) {
+        let Some(index) = self.selected else { return };
+        let Some(material) = driver.scene().get(index).map(|obj| obj.material()) else {
+            self.selected = None;
+            return;
+        };
+        match edit {
+            Edit::Move(offset) => {
+                let obj = &mut driver.scene_mut()[index];
+                obj.set_pos(obj.pos() + offset);
+            }
+            Edit::Scale(factor) => {
+                let obj = &mut driver.scene_mut()[index];
+                if let Some(radius) = obj.radius() {
+                    obj.set_radius((radius * factor).max(0.01));
+                }
+            }
+            Edit::Material(step) => {
+                let count = driver.materials().len() as isize;
+                if count > 0 {
+                    let next = (material as isize + step).rem_euclid(count) as usize;
+                    driver.scene_mut()[index].set_material(next);
+                    self.param = 0;
+                }
+            }
+            Edit::NextParam => {
+                if let Some(mat) = driver.materials().get(material) {
+                    self.param = (self.param + 1) % mat.params().len().max(1);
+                }
+            }
+            Edit::Param(step) => {
+                if let Some(mat) = driver.materials_mut().get_mut(material) {
+                    if let Some(mut param) = mat.params_mut().into_iter().nth(self.param) {
+                        param.set(*param.value + step);
+                    }
+                }
+            }
+        }
+    }
+
+    // One line summary of the selection, `None` when nothing is selected
+    pub fn describe(&self, driver: &RendererDriver) -> Option<String> {
+        let obj = driver.scene().get(self.selected?)?;
+        let (pos, material) = (obj.pos(), obj.material());
+        let mut text = format!("object {} at ({:.2}, {:.2}, {:.2})", self.selected?, pos.0, pos.1, pos.2);
+        if let Some(radius) = obj.radius() {
+            text += &format!(" r {radius:.2}");
+        }
+        text += &format!(" material {material}");
+        if let Some(mat) = driver.materials().get(material) {
+            if let Some(param) = mat.params().into_iter().nth(self.param) {
+                text += &format!(" {} {:.2}", param.name, param.value);
+            }
+        }
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interlumen_core::Color;
+    use interlumen_render::{BasicMaterial, Material, Sphere};
+
+    fn driver() -> RendererDriver {
+        let mut driver = RendererDriver::new();
+        for roughness in [0.2, 0.8] {
+            let material: Box<dyn Material> = Box::new(BasicMaterial {
+                albedo: Color::WHITE,
+                emit: Color::BLACK,
+                roughness,
+            });
+            driver.materials_mut().push(material);
+        }
+        driver.scene_mut().push(Box::new(Sphere::new(Vec3(0.0, 0.0, 3.0), 1.0, 0)));
+        driver
+    }
+
+    #[test]
+    fn edits_selected_object() {
+        let mut driver = driver();
+        let mut inspector = Inspector::default();
+        inspector.select(Some(0));
+        inspector.apply(&mut driver, Edit::Move(Vec3(1.0, 0.0, 0.0)));
+        inspector.apply(&mut driver, Edit::Scale(2.0));
+        inspector.apply(&mut driver, Edit::Material(1));
+        inspector.apply(&mut driver, Edit::Param(0.1));
+        assert_eq!(
+            inspector.describe(&driver).unwrap(),
+            "object 0 at (1.00, 0.00, 3.00) r 2.00 material 1 roughness 0.90"
+        );
+        assert!(driver.versions.scene > 0 && driver.versions.materials > 0);
+    }
+
+    #[test]
+    fn clamps_params() {
+        let mut driver = driver();
+        let mut inspector = Inspector::default();
+        inspector.select(Some(0));
+        inspector.apply(&mut driver, Edit::Param(1.0));
+        assert!(inspector.describe(&driver).unwrap().ends_with("roughness 1.00"));
+        inspector.apply(&mut driver, Edit::NextParam);
+        inspector.apply(&mut driver, Edit::Param(-2.0));
+        assert!(inspector.describe(&driver).unwrap().ends_with("albedo.r 0.00"));
+        for _ in 0..3 {
+            inspector.apply(&mut driver, Edit::NextParam);
+        }
+        inspector.apply(&mut driver, Edit::Param(5.0));
+        assert!(inspector.describe(&driver).unwrap().ends_with("emit.r 5.00"));
+    }
+
+    #[test]
+    fn nothing_selected() {
+        let mut driver = driver();
+        let mut inspector = Inspector::default();
+        inspector.apply(&mut driver, Edit::Scale(2.0));
+        assert_eq!(driver.scene()[0].radius(), Some(1.0));
+        assert!(inspector.describe(&driver).is_none());
+    }
+}
@@ -1,13 +1,24 @@
+mod animation;
+mod args;
 mod console;
+mod controls;
 mod engine;
+mod export;
 mod gui;
+mod hud;
+mod inspector;
+mod scene_file;
+mod terminal;
+mod worker;
 
+use args::{Args, Mode};
 use engine::Engine;
+use scene_file::SceneFile;
 
 use interlumen_core::*;
 use interlumen_render::*;
 
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 
 fn main() -> anyhow::Result<()> {
     let mut engine = Engine::new();
@@ -53,16 +64,17 @@ fn main() -> anyhow::Result<()> {
         albedo2: Color::new(0.8, 0.8, 0.1, 1.0),
     };
 
-    let mut materials: Vec<Box<dyn Material>> = Vec::new();
-    materials.push(Box::new(white_mat1));
-    materials.push(Box::new(floor_mat));
-    materials.push(Box::new(red_mat));
-    materials.push(Box::new(green_mat));
-    materials.push(Box::new(white_light_mat));
-    materials.push(Box::new(white_mat2));
-    materials.push(Box::new(white_mat3));
+    let materials: Vec<Box<dyn Material>> = vec![
+        Box::new(white_mat1),
+        Box::new(floor_mat),
+        Box::new(red_mat),
+        Box::new(green_mat),
+        Box::new(white_light_mat),
+        Box::new(white_mat2),
+        Box::new(white_mat3),
+    ];
 
-    engine.renderer_driver.materials = materials;
+    *engine.renderer_driver.materials_mut() = materials;
 
     let mut scene: Scene = Vec::new();
 
@@ -90,29 +102,31 @@ fn main() -> anyhow::Result<()> {
     let plane = Plane::new(Vec3(0.0, -0.5, 0.0), Vec3(0.0, 1.0, 0.0), 1);
     scene.push(Box::new(plane));
 
-    engine.renderer_driver.scene = scene;
+    *engine.renderer_driver.scene_mut() = scene;
     engine.renderer_driver.settings.max_iter = 100;
     engine.renderer_driver.settings.ray_depth = 10;
 
     engine.renderer_driver.camera.fov = 120.0;
 
-    let mut mode = 0;
-
-    if let Some(m) = std::env::args().skip(1).next() {
-        if m == "console" {
-            mode = 0;
-        } else if m == "gui" {
-            mode = 1;
-        }
+    let args = Args::parse(std::env::args().skip(1))?;
+    for scene in &args.scenes {
+        let scene = SceneFile::load(scene)?;
+        scene.apply(&mut engine.renderer_driver)?;
+        engine.animation = scene.animation(&engine.renderer_driver)?;
     }
+    engine.animate();
 
-    match mode {
+    match args.mode {
         #[cfg(feature = "console")]
-        0 => console::run(RwLock::new(engine))?,
+        Mode::Console => console::run(Arc::new(RwLock::new(engine)), &args)?,
 
         #[cfg(feature = "gui")]
-        1 => gui::run(RwLock::new(engine))?,
+        Mode::Gui => gui::run(Arc::new(RwLock::new(engine)), &args)?,
+
+        Mode::Render => export::run(&mut engine, &args)?,
 
+        // Reachable when the mode's feature is disabled
+        #[allow(unreachable_patterns)]
         _ => println!("Unknown mode"),
     }
     Ok(())
@@ -0,0 +1,1078 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail};
+use serde::Deserialize;
+
+use interlumen_core::{Color, Vec3};
+use interlumen_render::*;
+
+use crate::animation::{Animation, Interpolation, Keyframe, Target, Track};
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3(v[0], v[1], v[2])
+}
+
+fn color(c: [f32; 3]) -> Color {
+    Color::new(c[0], c[1], c[2], 1.0)
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn turbidity() -> f32 {
+    3.0
+}
+
+fn octaves() -> usize {
+    4
+}
+
+fn two() -> f32 {
+    2.0
+}
+
+fn half() -> f32 {
+    0.5
+}
+
+fn max_steps() -> usize {
+    64
+}
+
+#[derive(Deserialize)]
+pub struct SceneFile {
+    pub camera: Option<CameraDesc>,
+    pub settings: Option<SettingsDesc>,
+    #[serde(default)]
+    pub materials: Vec<MaterialDesc>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDesc>,
+    #[serde(default)]
+    pub lights: Vec<LightDesc>,
+    pub environment: Option<EnvironmentDesc>,
+    pub fog: Option<FogDesc>,
+    #[serde(default)]
+    pub animations: Vec<AnimationDesc>,
+    // Directory of the scene file, external assets are resolved against it
+    #[serde(skip)]
+    pub base: PathBuf,
+}
+
+#[derive(Deserialize)]
+pub struct CameraDesc {
+    pub pos: [f32; 3],
+    pub dir: [f32; 3],
+    pub fov: f32,
+    #[serde(default)]
+    pub aperture: f32,
+    #[serde(default = "one")]
+    pub focus_dist: f32,
+}
+
+#[derive(Deserialize)]
+pub struct FogDesc {
+    pub density: f32,
+    #[serde(default)]
+    pub falloff: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default = "white")]
+    pub albedo: [f32; 3],
+    #[serde(default)]
+    pub g: f32,
+}
+
+#[derive(Deserialize)]
+pub struct SettingsDesc {
+    pub max_iter: Option<usize>,
+    pub ray_depth: Option<usize>,
+    pub max_dist: Option<f32>,
+    pub hit_thres: Option<f32>,
+    pub seed: Option<u64>,
+    pub sampler: Option<SamplerDesc>,
+    pub target_error: Option<f32>,
+    pub min_samples: Option<usize>,
+    pub tile_size: Option<usize>,
+    pub tile_order: Option<TileOrderDesc>,
+    pub shutter: Option<f32>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TileOrderDesc {
+    Scanline,
+    Spiral,
+    Hilbert,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerDesc {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDesc {
+    Basic {
+        albedo: [f32; 3],
+        #[serde(default)]
+        emit: [f32; 3],
+        #[serde(default)]
+        roughness: f32,
+    },
+    Checker {
+        albedo1: [f32; 3],
+        albedo2: [f32; 3],
+    },
+    Textured {
+        #[serde(default = "white")]
+        albedo: [f32; 3],
+        // Black without an emission map, white with one so the map is used as is
+        emit: Option<[f32; 3]>,
+        #[serde(default = "one")]
+        roughness: f32,
+        albedo_map: Option<TextureDesc>,
+        emit_map: Option<TextureDesc>,
+        roughness_map: Option<TextureDesc>,
+        normal_map: Option<TextureDesc>,
+    },
+    Volume {
+        #[serde(default)]
+        absorption: f32,
+        scattering: f32,
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default)]
+        g: f32,
+    },
+    Principled(Box<PrincipledDesc>),
+    Subsurface {
+        albedo: [f32; 3],
+        mean_free_path: [f32; 3],
+        #[serde(default)]
+        g: f32,
+        #[serde(default = "max_steps")]
+        max_steps: usize,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextureDesc {
+    Image {
+        path: String,
+        #[serde(default)]
+        wrap: WrapDesc,
+        #[serde(default)]
+        filter: FilterDesc,
+        #[serde(default = "one")]
+        scale: f32,
+    },
+    Constant {
+        color: [f32; 3],
+    },
+    Noise {
+        #[serde(default)]
+        coords: CoordsDesc,
+        #[serde(default = "one")]
+        scale: f32,
+        #[serde(default)]
+        basis: BasisDesc,
+        #[serde(default = "octaves")]
+        octaves: usize,
+        #[serde(default = "two")]
+        lacunarity: f32,
+        #[serde(default = "half")]
+        gain: f32,
+    },
+    Marble {
+        #[serde(default)]
+        coords: CoordsDesc,
+        #[serde(default = "one")]
+        scale: f32,
+        #[serde(default = "one")]
+        frequency: f32,
+        #[serde(default = "two")]
+        turbulence: f32,
+        #[serde(default = "octaves")]
+        octaves: usize,
+    },
+    Wood {
+        #[serde(default)]
+        coords: CoordsDesc,
+        #[serde(default = "one")]
+        scale: f32,
+        #[serde(default = "one")]
+        frequency: f32,
+        #[serde(default = "half")]
+        turbulence: f32,
+    },
+    Stripes {
+        #[serde(default)]
+        coords: CoordsDesc,
+        #[serde(default = "one")]
+        scale: f32,
+        #[serde(default = "one")]
+        frequency: f32,
+        #[serde(default = "half")]
+        width: f32,
+    },
+    Gradient {
+        #[serde(default)]
+        coords: CoordsDesc,
+        #[serde(default = "one")]
+        scale: f32,
+        #[serde(default)]
+        axis: usize,
+    },
+    Ramp {
+        input: Box<TextureDesc>,
+        stops: Vec<StopDesc>,
+    },
+    Mix {
+        a: Box<TextureDesc>,
+        b: Box<TextureDesc>,
+        factor: Box<TextureDesc>,
+    },
+    Multiply {
+        a: Box<TextureDesc>,
+        b: Box<TextureDesc>,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct PrincipledDesc {
+    #[serde(default = "white")]
+    pub base_color: [f32; 3],
+    pub base_color_map: Option<TextureDesc>,
+    pub emit: Option<ParamDesc>,
+    pub roughness: Option<ParamDesc>,
+    pub metallic: Option<ParamDesc>,
+    pub specular: Option<ParamDesc>,
+    pub specular_tint: Option<ParamDesc>,
+    pub anisotropic: Option<ParamDesc>,
+    pub sheen: Option<ParamDesc>,
+    pub sheen_tint: Option<ParamDesc>,
+    pub clearcoat: Option<ParamDesc>,
+    pub clearcoat_gloss: Option<ParamDesc>,
+    pub transmission: Option<ParamDesc>,
+    pub ior: Option<f32>,
+    pub normal_map: Option<TextureDesc>,
+}
+
+// Material parameter given as a number, a color or a texture
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ParamDesc {
+    Value(f32),
+    Color([f32; 3]),
+    Texture(TextureDesc),
+}
+
+impl ParamDesc {
+    pub fn build(&self, base: &Path) -> anyhow::Result<Box<dyn Texture>> {
+        Ok(match self {
+            ParamDesc::Value(v) => Box::new(ConstantTexture(Color::new_value(*v, 1.0))),
+            ParamDesc::Color(c) => Box::new(ConstantTexture(color(*c))),
+            ParamDesc::Texture(t) => t.build(base)?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StopDesc {
+    pub pos: f32,
+    pub color: [f32; 3],
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CoordsDesc {
+    Uv,
+    #[default]
+    World,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BasisDesc {
+    #[default]
+    Perlin,
+    Simplex,
+    Worley,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapDesc {
+    #[default]
+    Repeat,
+    Mirror,
+    Clamp,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterDesc {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectDesc {
+    Sphere {
+        pos: [f32; 3],
+        radius: f32,
+        material: usize,
+    },
+    Plane {
+        pos: [f32; 3],
+        norm: [f32; 3],
+        material: usize,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LightDesc {
+    Point {
+        pos: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+    Spot {
+        pos: [f32; 3],
+        dir: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+        #[serde(default = "one")]
+        falloff: f32,
+    },
+    Directional {
+        dir: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default)]
+        angular_radius: f32,
+    },
+    Area {
+        pos: [f32; 3],
+        u: [f32; 3],
+        v: [f32; 3],
+        #[serde(default = "white")]
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EnvironmentDesc {
+    Color {
+        color: [f32; 3],
+    },
+    Gradient {
+        top: [f32; 3],
+        bottom: [f32; 3],
+    },
+    Sky {
+        sun_dir: [f32; 3],
+        #[serde(default = "turbidity")]
+        turbidity: f32,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default)]
+        sun_intensity: f32,
+    },
+    Hdr {
+        path: String,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default)]
+        rotation: f32,
+    },
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationTargetDesc {
+    Object,
+    Camera,
+    Material,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum InterpolationDesc {
+    Step,
+    #[default]
+    Linear,
+    Bezier,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum ValueDesc {
+    Number(f32),
+    Vector([f32; 3]),
+}
+
+#[derive(Deserialize)]
+pub struct KeyDesc {
+    pub time: f32,
+    pub value: ValueDesc,
+}
+
+#[derive(Deserialize)]
+pub struct AnimationDesc {
+    pub target: AnimationTargetDesc,
+    // Object or material index
+    #[serde(default)]
+    pub index: usize,
+    pub property: String,
+    #[serde(default)]
+    pub interpolation: InterpolationDesc,
+    #[serde(default)]
+    pub repeat: bool,
+    pub keys: Vec<KeyDesc>,
+}
+
+fn mapping(coords: CoordsDesc, scale: f32) -> Mapping {
+    let coords = match coords {
+        CoordsDesc::Uv => Coords::Uv,
+        CoordsDesc::World => Coords::World,
+    };
+    Mapping::new(coords, scale)
+}
+
+impl TextureDesc {
+    pub fn build(&self, base: &Path) -> anyhow::Result<Box<dyn Texture>> {
+        Ok(match self {
+            TextureDesc::Image {
+                path,
+                wrap,
+                filter,
+                scale,
+            } => Box::new(ImageTexture {
+                image: Image::load(base.join(path))?,
+                wrap: match wrap {
+                    WrapDesc::Repeat => WrapMode::Repeat,
+                    WrapDesc::Mirror => WrapMode::Mirror,
+                    WrapDesc::Clamp => WrapMode::Clamp,
+                },
+                filter: match filter {
+                    FilterDesc::Nearest => Filter::Nearest,
+                    FilterDesc::Bilinear => Filter::Bilinear,
+                },
+                scale: *scale,
+            }),
+            TextureDesc::Constant { color: c } => Box::new(ConstantTexture(color(*c))),
+            TextureDesc::Noise {
+                coords,
+                scale,
+                basis,
+                octaves,
+                lacunarity,
+                gain,
+            } => Box::new(NoiseTexture {
+                mapping: mapping(*coords, *scale),
+                basis: match basis {
+                    BasisDesc::Perlin => NoiseBasis::Perlin,
+                    BasisDesc::Simplex => NoiseBasis::Simplex,
+                    BasisDesc::Worley => NoiseBasis::Worley,
+                },
+                octaves: *octaves,
+                lacunarity: *lacunarity,
+                gain: *gain,
+            }),
+            TextureDesc::Marble {
+                coords,
+                scale,
+                frequency,
+                turbulence,
+                octaves,
+            } => Box::new(MarbleTexture {
+                mapping: mapping(*coords, *scale),
+                frequency: *frequency,
+                turbulence: *turbulence,
+                octaves: *octaves,
+            }),
+            TextureDesc::Wood {
+                coords,
+                scale,
+                frequency,
+                turbulence,
+            } => Box::new(WoodTexture {
+                mapping: mapping(*coords, *scale),
+                frequency: *frequency,
+                turbulence: *turbulence,
+            }),
+            TextureDesc::Stripes {
+                coords,
+                scale,
+                frequency,
+                width,
+            } => Box::new(StripesTexture {
+                mapping: mapping(*coords, *scale),
+                frequency: *frequency,
+                width: *width,
+            }),
+            TextureDesc::Gradient {
+                coords,
+                scale,
+                axis,
+            } => Box::new(GradientTexture {
+                mapping: mapping(*coords, *scale),
+                axis: *axis,
+            }),
+            TextureDesc::Ramp { input, stops } => {
+                let mut stops: Vec<_> = stops.iter().map(|s| (s.pos, color(s.color))).collect();
+                stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+                Box::new(ColorRamp {
+                    input: input.build(base)?,
+                    stops,
+                })
+            }
+            TextureDesc::Mix { a, b, factor } => Box::new(MixTexture {
+                a: a.build(base)?,
+                b: b.build(base)?,
+                factor: factor.build(base)?,
+            }),
+            TextureDesc::Multiply { a, b } => Box::new(MultiplyTexture {
+                a: a.build(base)?,
+                b: b.build(base)?,
+            }),
+        })
+    }
+}
+
+fn texture(desc: &Option<TextureDesc>, base: &Path) -> anyhow::Result<Option<Box<dyn Texture>>> {
+    desc.as_ref().map(|t| t.build(base)).transpose()
+}
+
+impl MaterialDesc {
+    pub fn build(&self, base: &Path) -> anyhow::Result<Box<dyn Material>> {
+        Ok(match self {
+            MaterialDesc::Basic {
+                albedo,
+                emit,
+                roughness,
+            } => Box::new(BasicMaterial {
+                albedo: color(*albedo),
+                emit: color(*emit),
+                roughness: *roughness,
+            }),
+            MaterialDesc::Checker { albedo1, albedo2 } => Box::new(CheckerMaterial {
+                albedo1: color(*albedo1),
+                albedo2: color(*albedo2),
+            }),
+            MaterialDesc::Textured {
+                albedo,
+                emit,
+                roughness,
+                albedo_map,
+                emit_map,
+                roughness_map,
+                normal_map,
+            } => Box::new(TexturedMaterial {
+                albedo: color(*albedo),
+                emit: color(emit.unwrap_or(if emit_map.is_some() { white() } else { [0.0; 3] })),
+                roughness: *roughness,
+                albedo_map: texture(albedo_map, base)?,
+                emit_map: texture(emit_map, base)?,
+                roughness_map: texture(roughness_map, base)?,
+                normal_map: texture(normal_map, base)?,
+            }),
+            MaterialDesc::Volume {
+                absorption,
+                scattering,
+                color: c,
+                g,
+            } => Box::new(VolumeMaterial {
+                medium: Medium {
+                    absorption: *absorption,
+                    scattering: *scattering,
+                    color: color(*c),
+                    g: *g,
+                },
+            }),
+            MaterialDesc::Principled(desc) => {
+                let PrincipledDesc {
+                    base_color,
+                    base_color_map,
+                    emit,
+                    roughness,
+                    metallic,
+                    specular,
+                    specular_tint,
+                    anisotropic,
+                    sheen,
+                    sheen_tint,
+                    clearcoat,
+                    clearcoat_gloss,
+                    transmission,
+                    ior,
+                    normal_map,
+                } = desc.as_ref();
+                let mut material = PrincipledMaterial::new(color(*base_color));
+                if let Some(map) = texture(base_color_map, base)? {
+                    material.base_color = Box::new(MultiplyTexture {
+                        a: material.base_color,
+                        b: map,
+                    });
+                }
+                let params = [
+                    (emit, &mut material.emit),
+                    (roughness, &mut material.roughness),
+                    (metallic, &mut material.metallic),
+                    (specular, &mut material.specular),
+                    (specular_tint, &mut material.specular_tint),
+                    (anisotropic, &mut material.anisotropic),
+                    (sheen, &mut material.sheen),
+                    (sheen_tint, &mut material.sheen_tint),
+                    (clearcoat, &mut material.clearcoat),
+                    (clearcoat_gloss, &mut material.clearcoat_gloss),
+                    (transmission, &mut material.transmission),
+                ];
+                for (desc, param) in params {
+                    if let Some(desc) = desc {
+                        *param = desc.build(base)?;
+                    }
+                }
+                if let Some(ior) = ior {
+                    material.ior = *ior;
+                }
+                material.normal_map = texture(normal_map, base)?;
+                Box::new(material)
+            }
+            MaterialDesc::Subsurface {
+                albedo,
+                mean_free_path,
+                g,
+                max_steps,
+            } => Box::new(SubsurfaceMaterial {
+                subsurface: Subsurface {
+                    albedo: color(*albedo),
+                    mean_free_path: color(*mean_free_path),
+                    g: *g,
+                    max_steps: *max_steps,
+                },
+            }),
+        })
+    }
+}
+
+impl ObjectDesc {
+    pub fn build(&self) -> Box<dyn Object> {
+        match *self {
+            ObjectDesc::Sphere {
+                pos,
+                radius,
+                material,
+            } => Box::new(Sphere::new(vec3(pos), radius, material)),
+            ObjectDesc::Plane {
+                pos,
+                norm,
+                material,
+            } => Box::new(Plane::new(vec3(pos), vec3(norm).norm(), material)),
+        }
+    }
+}
+
+impl LightDesc {
+    pub fn build(&self) -> Box<dyn Light> {
+        match *self {
+            LightDesc::Point {
+                pos,
+                color: c,
+                intensity,
+            } => Box::new(PointLight {
+                pos: vec3(pos),
+                color: color(c),
+                intensity,
+            }),
+            LightDesc::Spot {
+                pos,
+                dir,
+                color: c,
+                intensity,
+                inner_angle,
+                outer_angle,
+                falloff,
+            } => Box::new(SpotLight {
+                pos: vec3(pos),
+                dir: vec3(dir).norm(),
+                color: color(c),
+                intensity,
+                inner_angle,
+                outer_angle,
+                falloff,
+            }),
+            LightDesc::Directional {
+                dir,
+                color: c,
+                intensity,
+                angular_radius,
+            } => Box::new(DirectionalLight {
+                dir: vec3(dir).norm(),
+                color: color(c),
+                intensity,
+                angular_radius,
+            }),
+            LightDesc::Area {
+                pos,
+                u,
+                v,
+                color: c,
+                intensity,
+            } => Box::new(AreaLight {
+                pos: vec3(pos),
+                u: vec3(u),
+                v: vec3(v),
+                color: color(c),
+                intensity,
+            }),
+        }
+    }
+}
+
+impl EnvironmentDesc {
+    pub fn build(&self, base: &Path) -> anyhow::Result<Environment> {
+        Ok(match self {
+            EnvironmentDesc::Color { color: c } => Environment::Color(color(*c)),
+            EnvironmentDesc::Gradient { top, bottom } => Environment::Gradient {
+                top: color(*top),
+                bottom: color(*bottom),
+            },
+            EnvironmentDesc::Sky {
+                sun_dir,
+                turbidity,
+                intensity,
+                sun_intensity,
+            } => {
+                let mut sky = Sky::new(vec3(*sun_dir), *turbidity, *intensity);
+                sky.sun_intensity = *sun_intensity;
+                Environment::Sky(sky)
+            }
+            EnvironmentDesc::Hdr {
+                path,
+                intensity,
+                rotation,
+            } => {
+                let image = Image::load_hdr(base.join(path))?;
+                Environment::Map(EnvironmentMap::new(image, *intensity, *rotation))
+            }
+        })
+    }
+}
+
+impl AnimationDesc {
+    // Checks the track against the scene it animates, `driver` must already hold the scene
+    pub fn build(&self, driver: &RendererDriver) -> anyhow::Result<Track> {
+        let (index, property) = (self.index, self.property.as_str());
+        let (target, vector) = match (self.target, property) {
+            (AnimationTargetDesc::Object, "pos" | "rotation") => {
+                if index >= driver.scene().len() {
+                    bail!("animated object {index} does not exist");
+                }
+                match property {
+                    "pos" => (Target::ObjectPos(index), true),
+                    _ => (Target::ObjectRotation(index), true),
+                }
+            }
+            (AnimationTargetDesc::Camera, "pos") => (Target::CameraPos, true),
+            (AnimationTargetDesc::Camera, "dir") => (Target::CameraDir, true),
+            (AnimationTargetDesc::Camera, "fov") => (Target::CameraFov, false),
+            (AnimationTargetDesc::Camera, "aperture") => (Target::CameraAperture, false),
+            (AnimationTargetDesc::Camera, "focus_dist") => (Target::CameraFocusDist, false),
+            (AnimationTargetDesc::Material, _) => {
+                let material = driver
+                    .materials()
+                    .get(index)
+                    .ok_or_else(|| anyhow!("animated material {index} does not exist"))?;
+                let params: Vec<&str> = material.params().into_iter().map(|p| p.name).collect();
+                let vector = if params.contains(&property) {
+                    false
+                } else if params.contains(&format!("{property}.r").as_str()) {
+                    true
+                } else {
+                    bail!("material {index} has no parameter {property}");
+                };
+                (Target::Material(index, property.to_string()), vector)
+            }
+            _ => bail!("property {property} can't be animated"),
+        };
+        if self.keys.is_empty() {
+            bail!("animation of {property} has no keys");
+        }
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| {
+                let value = match (key.value, vector) {
+                    (ValueDesc::Number(v), false) => Vec3(v, 0.0, 0.0),
+                    (ValueDesc::Vector(v), true) => vec3(v),
+                    (_, true) => bail!("{property} keys need [x, y, z] values"),
+                    (_, false) => bail!("{property} keys need number values"),
+                };
+                Ok(Keyframe {
+                    time: key.time,
+                    value,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let interpolation = match self.interpolation {
+            InterpolationDesc::Step => Interpolation::Step,
+            InterpolationDesc::Linear => Interpolation::Linear,
+            InterpolationDesc::Bezier => Interpolation::Bezier,
+        };
+        Ok(Track::new(target, interpolation, self.repeat, keys))
+    }
+}
+
+impl SceneFile {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let mut scene: SceneFile = toml::from_str(&text)?;
+        scene.base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(scene)
+    }
+
+    pub fn apply(&self, driver: &mut RendererDriver) -> anyhow::Result<()> {
+        if let Some(camera) = &self.camera {
+            driver.camera = Camera {
+                aperture: camera.aperture,
+                focus_dist: camera.focus_dist,
+                ..Camera::new(vec3(camera.pos), vec3(camera.dir).norm(), camera.fov)
+            };
+        }
+        if let Some(settings) = &self.settings {
+            let s = &mut driver.settings;
+            s.max_iter = settings.max_iter.unwrap_or(s.max_iter);
+            s.ray_depth = settings.ray_depth.unwrap_or(s.ray_depth);
+            s.max_dist = settings.max_dist.unwrap_or(s.max_dist);
+            s.hit_thres = settings.hit_thres.unwrap_or(s.hit_thres);
+            s.seed = settings.seed.unwrap_or(s.seed);
+            s.target_error = settings.target_error.unwrap_or(s.target_error);
+            s.min_samples = settings.min_samples.unwrap_or(s.min_samples);
+            s.tile_size = settings.tile_size.unwrap_or(s.tile_size);
+            s.shutter = settings.shutter.unwrap_or(s.shutter);
+            if let Some(order) = settings.tile_order {
+                s.tile_order = match order {
+                    TileOrderDesc::Scanline => TileOrder::Scanline,
+                    TileOrderDesc::Spiral => TileOrder::Spiral,
+                    TileOrderDesc::Hilbert => TileOrder::Hilbert,
+                };
+            }
+            if let Some(sampler) = settings.sampler {
+                s.sampler = match sampler {
+                    SamplerDesc::Independent => SamplerKind::Independent,
+                    SamplerDesc::Stratified => SamplerKind::Stratified,
+                    SamplerDesc::Halton => SamplerKind::Halton,
+                    SamplerDesc::Sobol => SamplerKind::Sobol,
+                    SamplerDesc::BlueNoise => SamplerKind::BlueNoise,
+                };
+            }
+        }
+        *driver.materials_mut() = self
+            .materials
+            .iter()
+            .map(|m| m.build(&self.base))
+            .collect::<anyhow::Result<_>>()?;
+        *driver.scene_mut() = self.objects.iter().map(ObjectDesc::build).collect();
+        *driver.lights_mut() = self.lights.iter().map(LightDesc::build).collect();
+        if let Some(environment) = &self.environment {
+            *driver.environment_mut() = environment.build(&self.base)?;
+        }
+        *driver.fog_mut() = self.fog.as_ref().map(|fog| HeightFog {
+            density: fog.density,
+            falloff: fog.falloff,
+            height: fog.height,
+            albedo: color(fog.albedo),
+            g: fog.g,
+        });
+        Ok(())
+    }
+
+    // Has to be called after `apply`, tracks are checked against the loaded objects and materials
+    pub fn animation(&self, driver: &RendererDriver) -> anyhow::Result<Animation> {
+        let tracks = self
+            .animations
+            .iter()
+            .map(|desc| desc.build(driver))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Animation { tracks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(path: &str) -> RendererDriver {
+        let mut driver = RendererDriver::new();
+        SceneFile::load(path).unwrap().apply(&mut driver).unwrap();
+        driver
+    }
+
+    // Whether a material gives more than one albedo over the given surface points
+    fn varies(material: &dyn Material, points: impl Iterator<Item = (Vec3, Vec3)>) -> bool {
+        let albedos: Vec<Color> = points.map(|(uv, point)| material.get_color(uv, point).albedo).collect();
+        albedos.iter().any(|&albedo| albedo != albedos[0])
+    }
+
+    #[test]
+    fn load_lights_scene() {
+        let driver = load("scenes/lights.toml");
+        assert_eq!(driver.camera.fov, 120.0);
+        assert_eq!(driver.materials().len(), 3);
+        assert_eq!(driver.scene().len(), 3);
+        assert_eq!(driver.lights().len(), 4);
+        assert_eq!(driver.settings.max_iter, 100);
+        assert_eq!(driver.settings.seed, 1);
+        assert_eq!(driver.settings.sampler, SamplerKind::Sobol);
+        assert_eq!(driver.settings.target_error, 0.02);
+        assert_eq!(driver.settings.tile_order, TileOrder::Spiral);
+    }
+
+    #[test]
+    fn load_sky_scene() {
+        let driver = load("scenes/sky.toml");
+        let Environment::Sky(sky) = &driver.environment() else {
+            panic!("expected a sky");
+        };
+        assert_eq!((sky.turbidity, sky.intensity, sky.sun_intensity), (3.0, 1.0, 20.0));
+        // The sun outshines the sky around it
+        let sun = driver.environment().radiance(Vec3(0.3, 0.5, 0.4).norm());
+        let sky = driver.environment().radiance(Vec3(-0.3, 0.5, -0.4).norm());
+        assert!(sun.g > sky.g * 10.0);
+    }
+
+    #[test]
+    fn load_textured_scene() {
+        let driver = load("scenes/textured.toml");
+        let image = Image::load_ppm("scenes/textures/bricks.ppm").unwrap();
+        // Nearest filtering repeated 4 times, so u = 0.1 lands 40% into the image
+        let uv = Vec3(0.1, 0.1, 0.0);
+        let (x, y) = (0.4 * image.width as f32, 0.6 * image.height as f32);
+        let color = driver.materials()[0].get_color(uv, Vec3::default());
+        assert_eq!(color.albedo, image.get(x as usize, y as usize));
+        let points = (0..16).map(|i| (Vec3(i as f32 / 16.0, 0.5, 0.0), Vec3::default()));
+        assert!(varies(driver.materials()[1].as_ref(), points));
+    }
+
+    #[test]
+    fn emit_map_without_emit() {
+        let map = "{ type = \"image\", path = \"scenes/textures/bricks.ppm\" }";
+        let text = format!("[[materials]]\ntype = \"textured\"\nemit_map = {map}\n[[materials]]\ntype = \"textured\"\nalbedo_map = {map}");
+        let scene: SceneFile = toml::from_str(&text).unwrap();
+        let mut driver = RendererDriver::new();
+        scene.apply(&mut driver).unwrap();
+        // The map is used as is, like an albedo map on a white material
+        let uv = Vec3(0.3, 0.6, 0.0);
+        let point = Vec3::default();
+        assert_eq!(driver.materials()[0].get_color(uv, point).emit, driver.materials()[1].get_color(uv, point).albedo);
+    }
+
+    #[test]
+    fn load_procedural_scene() {
+        let driver = load("scenes/procedural.toml");
+        // Marble and wood follow the position, the stripes the uv coordinates
+        let along = |i: i32| Vec3(i as f32 * 0.13, 0.0, 2.0);
+        assert!(varies(driver.materials()[0].as_ref(), (0..16).map(|i| (Vec3::default(), along(i)))));
+        assert!(varies(driver.materials()[1].as_ref(), (0..16).map(|i| (Vec3::default(), along(i)))));
+        assert!(varies(driver.materials()[2].as_ref(), (0..16).map(|i| (along(i) * 0.1, Vec3::default()))));
+        let wood = driver.materials()[1].get_color(Vec3::default(), along(3)).albedo;
+        assert!(wood.r > wood.g && wood.g > wood.b);
+    }
+
+    #[test]
+    fn load_volume_scene() {
+        let driver = load("scenes/volumes.toml");
+        let fog = driver.fog().unwrap();
+        assert_eq!((fog.density, fog.falloff, fog.height, fog.g), (0.25, 0.6, -0.5, 0.6));
+        assert_eq!(fog.albedo, Color::new(0.9, 0.9, 1.0, 1.0));
+        let medium = driver.materials()[1].medium().unwrap();
+        assert_eq!((medium.absorption, medium.scattering, medium.g), (0.2, 3.0, 0.3));
+        assert_eq!(medium.extinction(), 3.2);
+        assert!(driver.materials()[0].medium().is_none());
+    }
+
+    #[test]
+    fn load_subsurface_scene() {
+        let driver = load("scenes/subsurface.toml");
+        let skin = driver.materials()[1].subsurface().unwrap();
+        assert_eq!(skin.mean_free_path, Color::new(0.3, 0.12, 0.06, 1.0));
+        // Red travels furthest, so it's the least dense
+        let [r, g, b] = skin.extinction();
+        assert!(r < g && g < b);
+        assert_eq!(driver.materials()[2].subsurface().unwrap().g, 0.3);
+        assert!(driver.materials()[0].subsurface().is_none());
+    }
+
+    #[test]
+    fn load_principled_scene() {
+        let driver = load("scenes/principled.toml");
+        let color = driver.materials()[1].get_color(Vec3::default(), Vec3::default());
+        assert_eq!(color.metallic, 1.0);
+        assert_eq!(color.anisotropic, 0.8);
+    }
+
+    #[test]
+    fn load_animated_scene() {
+        let mut driver = load("scenes/animated.toml");
+        let animation = SceneFile::load("scenes/animated.toml").unwrap().animation(&driver).unwrap();
+        assert_eq!(animation.tracks.len(), 5);
+        assert_eq!(driver.settings.shutter, 0.04);
+        animation.apply(&mut driver, 1.0, None);
+        assert_eq!(driver.scene()[0].pos(), Vec3(2.0, 0.0, 3.0));
+        // The swing is blurred while the shutter is open
+        animation.apply(&mut driver, 1.5, None);
+        assert!(driver.scene()[0].motion().len() > 0.0);
+        let albedo: Vec<f32> = driver.materials()[0].params()[1..4].iter().map(|p| p.value).collect();
+        assert_eq!(albedo, [0.2, 0.3, 0.8]);
+        animation.apply(&mut driver, 3.5, None);
+        assert_eq!(driver.camera.fov, 70.0);
+        // The camera track doesn't repeat and stays at its last key
+        animation.apply(&mut driver, 10.0, None);
+        assert_eq!(driver.camera.pos, Vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn reject_bad_animations() {
+        let animation = |track: &str| {
+            let text = format!("[[materials]]\ntype = \"basic\"\nalbedo = [1, 1, 1]\n[[objects]]\ntype = \"sphere\"\npos = [0, 0, 0]\nradius = 1\nmaterial = 0\n[[animations]]\n{track}");
+            let scene: SceneFile = toml::from_str(&text).unwrap();
+            let mut driver = RendererDriver::new();
+            scene.apply(&mut driver).unwrap();
+            scene.animation(&driver)
+        };
+        assert!(animation("target = \"material\"\nproperty = \"albedo\"\nkeys = [{ time = 0, value = [1, 0, 0] }]").is_ok());
+        assert!(animation("target = \"object\"\nindex = 1\nproperty = \"pos\"\nkeys = [{ time = 0, value = [1, 0, 0] }]").is_err());
+        assert!(animation("target = \"camera\"\nproperty = \"fov\"\nkeys = [{ time = 0, value = [1, 0, 0] }]").is_err());
+        assert!(animation("target = \"material\"\nproperty = \"metallic\"\nkeys = [{ time = 0, value = 1 }]").is_err());
+        assert!(animation("target = \"camera\"\nproperty = \"fov\"\nkeys = []").is_err());
+    }
+}
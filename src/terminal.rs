@@ -0,0 +1,668 @@
+use std::{
+    fmt::Write as _,
+    io::{stdout, Write},
+    str::FromStr,
+    time::Duration,
+};
+
+use crossterm::{cursor::MoveTo, queue, style::Print};
+use interlumen_core::Color;
+
+const UPPER_HALF: char = '▀';
+
+// Darkest to brightest
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+// Bit of each dot in a braille cell, indexed by [row][column]
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+// xterm's default 16 colour palette
+const ANSI16: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+// Channel levels of the 6x6x6 cube in the 256 colour palette
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// Sixel colour registers, a 6x7x6 cube with green getting the extra level
+const SIXEL_LEVELS: [usize; 3] = [6, 7, 6];
+
+// Cell size assumed when the terminal doesn't report its size in pixels
+const DEFAULT_CELL_PIXELS: (usize, usize) = (8, 16);
+
+const BAYER4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    // Guesses from the environment the way most terminal programs do
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    // Distance between neighbouring palette colours, how far dithering may push a pixel
+    fn spread(&self) -> f32 {
+        match self {
+            ColorDepth::TrueColor => 0.0,
+            ColorDepth::Ansi256 => 40.0,
+            ColorDepth::Ansi16 => 128.0,
+        }
+    }
+}
+
+impl FromStr for ColorDepth {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "truecolor" | "24bit" => Ok(ColorDepth::TrueColor),
+            "256" => Ok(ColorDepth::Ansi256),
+            "16" => Ok(ColorDepth::Ansi16),
+            _ => anyhow::bail!("unknown colour depth {s}, expected truecolor, 256 or 16"),
+        }
+    }
+}
+
+// How frames are turned into terminal output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Output {
+    #[default]
+    HalfBlock,
+    // Plain characters by brightness, no colour escapes
+    Ascii,
+    // 2x4 dots per cell, no colour escapes
+    Braille,
+    // Real images at the terminal's pixel resolution
+    Sixel,
+    Kitty,
+}
+
+impl Output {
+    // Cells are about twice as tall as wide, image pixels are square
+    pub fn pixel_ratio(&self) -> f32 {
+        match self {
+            Output::HalfBlock | Output::Braille | Output::Sixel | Output::Kitty => 1.0,
+            Output::Ascii => 2.0,
+        }
+    }
+
+    // Image size filling `cells`, each of them `cell_pixels` screen pixels large
+    pub fn render_size(&self, (cols, rows): (usize, usize), (cell_w, cell_h): (usize, usize)) -> (usize, usize) {
+        match self {
+            Output::HalfBlock => (cols, rows * 2),
+            Output::Ascii => (cols, rows),
+            Output::Braille => (cols * 2, rows * 4),
+            // The last row stays free, an image reaching it would scroll the screen.
+            // Sixel images are sent in bands of six rows.
+            Output::Sixel => (cols * cell_w, rows.saturating_sub(1) * cell_h / 6 * 6),
+            Output::Kitty => (cols * cell_w, rows * cell_h),
+        }
+    }
+
+    pub fn encode(
+        &self,
+        pixels: &[Color],
+        width: usize,
+        height: usize,
+        cells: (usize, usize),
+        depth: ColorDepth,
+    ) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Output::HalfBlock => encode_half_blocks(pixels, width, height, depth),
+            Output::Ascii => encode_ascii(pixels, width, height),
+            Output::Braille => encode_braille(pixels, width, height),
+            Output::Sixel => encode_sixel(pixels, width, height),
+            Output::Kitty => encode_kitty(pixels, width, height, cells),
+        }
+    }
+}
+
+impl FromStr for Output {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "halfblock" => Ok(Output::HalfBlock),
+            "ascii" => Ok(Output::Ascii),
+            "braille" => Ok(Output::Braille),
+            "sixel" => Ok(Output::Sixel),
+            "kitty" => Ok(Output::Kitty),
+            _ => anyhow::bail!("unknown output {s}, expected halfblock, ascii, braille, sixel or kitty"),
+        }
+    }
+}
+
+// What the terminal told us about itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities {
+    pub kitty: bool,
+    pub sixel: bool,
+    // Size of the text area in pixels
+    pub pixels: Option<(usize, usize)>,
+}
+
+// Cells of a terminal `width` x `height` pixels large when it can't be asked
+pub fn default_cells(width: usize, height: usize) -> (usize, usize) {
+    let (cell_w, cell_h) = DEFAULT_CELL_PIXELS;
+    ((width / cell_w).max(1), (height / cell_h).max(1))
+}
+
+impl Capabilities {
+    // Asks for kitty graphics support, the window size in pixels and the device
+    // attributes. Every terminal answers the last query, so reading stops there.
+    // Needs raw mode, otherwise the answers are echoed and line buffered.
+    pub fn probe() -> anyhow::Result<Self> {
+        let mut out = stdout();
+        out.write_all(b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[14t\x1b[c")?;
+        out.flush()?;
+        #[cfg(unix)]
+        let response = {
+            use std::os::fd::AsRawFd;
+            Capabilities::read_answer(std::io::stdin().as_raw_fd(), Duration::from_millis(500))
+        };
+        #[cfg(not(unix))]
+        let response: Vec<u8> = Vec::new();
+        Ok(Capabilities::parse(&String::from_utf8_lossy(&response)))
+    }
+
+    // Reads a byte at a time straight from the descriptor, so keys typed after the
+    // answer are left for crossterm and nothing is still reading once this returns
+    #[cfg(unix)]
+    fn read_answer(fd: std::os::fd::RawFd, timeout: Duration) -> Vec<u8> {
+        let deadline = std::time::Instant::now() + timeout;
+        let mut response = Vec::new();
+        while !Capabilities::answered(&response) {
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            let mut poll = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: `poll` points to a single pollfd that outlives the call
+            let ready = unsafe { libc::poll(&mut poll, 1, left.as_millis().max(1) as libc::c_int) };
+            if ready < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            if ready <= 0 {
+                break;
+            }
+            let mut byte = 0u8;
+            // SAFETY: reads at most one byte into `byte`
+            if unsafe { libc::read(fd, (&mut byte as *mut u8).cast(), 1) } != 1 {
+                break;
+            }
+            response.push(byte);
+        }
+        response
+    }
+
+    // The device attributes answer looks like ESC [ ? 62 ; 4 c
+    fn answered(response: &[u8]) -> bool {
+        let Some(start) = response.windows(3).position(|w| w == b"\x1b[?") else {
+            return false;
+        };
+        response[start..].ends_with(b"c")
+    }
+
+    fn parse(response: &str) -> Self {
+        let attributes = response
+            .split_once("\x1b[?")
+            .and_then(|(_, rest)| rest.split_once('c'))
+            .map_or("", |(params, _)| params);
+        let pixels = response
+            .split_once("\x1b[4;")
+            .and_then(|(_, rest)| rest.split_once('t'))
+            .and_then(|(size, _)| size.split_once(';'))
+            .and_then(|(h, w)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .filter(|&(w, h)| w > 0 && h > 0);
+        Self {
+            kitty: response.contains("\x1b_Gi=31;OK"),
+            sixel: attributes.split(';').any(|param| param == "4"),
+            pixels,
+        }
+    }
+
+    pub fn preferred_output(&self) -> Output {
+        if self.kitty {
+            Output::Kitty
+        } else if self.sixel {
+            Output::Sixel
+        } else {
+            Output::HalfBlock
+        }
+    }
+
+    // Pixel size of one character cell when the terminal has `cells` of them
+    pub fn cell_pixels(&self, (cols, rows): (usize, usize)) -> (usize, usize) {
+        match self.pixels {
+            Some((w, h)) if cols > 0 && rows > 0 => ((w / cols).max(1), (h / rows).max(1)),
+            _ => DEFAULT_CELL_PIXELS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellColor {
+    Rgb([u8; 3]),
+    Index(u8),
+}
+
+impl CellColor {
+    fn sgr(&self, depth: ColorDepth, background: bool, out: &mut String) {
+        let _ = match (*self, depth) {
+            (CellColor::Rgb([r, g, b]), _) => write!(out, "\x1b[{};2;{r};{g};{b}m", if background { 48 } else { 38 }),
+            (CellColor::Index(i), ColorDepth::Ansi16) => {
+                let base = match (background, i < 8) {
+                    (false, true) => 30,
+                    (false, false) => 90 - 8,
+                    (true, true) => 40,
+                    (true, false) => 100 - 8,
+                };
+                write!(out, "\x1b[{}m", base + i as u32)
+            }
+            (CellColor::Index(i), _) => write!(out, "\x1b[{};5;{i}m", if background { 48 } else { 38 }),
+        };
+    }
+}
+
+fn distance(a: [u8; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] as f32 - b[i]).powi(2)).sum()
+}
+
+fn nearest_256(rgb: [f32; 3]) -> u8 {
+    let level = |v: f32| {
+        (0..CUBE.len())
+            .min_by(|&a, &b| (CUBE[a] as f32 - v).abs().total_cmp(&(CUBE[b] as f32 - v).abs()))
+            .unwrap()
+    };
+    let (r, g, b) = (level(rgb[0]), level(rgb[1]), level(rgb[2]));
+    let cube = [CUBE[r], CUBE[g], CUBE[b]];
+    // The grey ramp 232..=255 covers 8..=238 in steps of 10
+    let grey = (((rgb[0] + rgb[1] + rgb[2]) / 3.0 - 8.0) / 10.0).round().clamp(0.0, 23.0) as u8;
+    let level = 8 + grey * 10;
+    if distance([level; 3], rgb) < distance(cube, rgb) {
+        232 + grey
+    } else {
+        (16 + 36 * r + 6 * g + b) as u8
+    }
+}
+
+fn nearest_16(rgb: [f32; 3]) -> u8 {
+    (0..16)
+        .min_by(|&a, &b| distance(ANSI16[a], rgb).total_cmp(&distance(ANSI16[b], rgb)))
+        .unwrap() as u8
+}
+
+// Maps a pixel to the palette, with an ordered dither keyed by the pixel position
+fn quantize(color: Color, x: usize, y: usize, depth: ColorDepth) -> CellColor {
+    let c = color.as_color32();
+    if depth == ColorDepth::TrueColor {
+        return CellColor::Rgb([c.r, c.g, c.b]);
+    }
+    let offset = ((BAYER4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5) * depth.spread();
+    let rgb = [c.r as f32 + offset, c.g as f32 + offset, c.b as f32 + offset];
+    match depth {
+        ColorDepth::Ansi16 => CellColor::Index(nearest_16(rgb)),
+        _ => CellColor::Index(nearest_256(rgb)),
+    }
+}
+
+// Encodes two pixel rows per character cell, the top one as the foreground of
+// an upper half block and the bottom one as its background. Colour escapes are
+// only emitted when a colour actually changes.
+pub fn encode_half_blocks(pixels: &[Color], width: usize, height: usize, depth: ColorDepth) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut cell = String::new();
+    for row in 0..height.div_ceil(2) {
+        queue!(out, MoveTo(0, row as u16))?;
+        let (mut fg, mut bg) = (None, None);
+        cell.clear();
+        for x in 0..width {
+            let (y0, y1) = (row * 2, (row * 2 + 1).min(height - 1));
+            let top = quantize(pixels[y0 * width + x], x, y0, depth);
+            let bottom = quantize(pixels[y1 * width + x], x, y1, depth);
+            if bg != Some(bottom) {
+                bottom.sgr(depth, true, &mut cell);
+                bg = Some(bottom);
+            }
+            // A space only needs the background
+            if top == bottom {
+                cell.push(' ');
+                continue;
+            }
+            if fg != Some(top) {
+                top.sgr(depth, false, &mut cell);
+                fg = Some(top);
+            }
+            cell.push(UPPER_HALF);
+        }
+        queue!(out, Print(&cell), Print("\x1b[0m"))?;
+    }
+    Ok(out)
+}
+
+fn brightness(color: Color) -> f32 {
+    color.luminance().clamp(0.0, 1.0)
+}
+
+// One line of characters per pixel row
+pub fn ascii_lines(pixels: &[Color], width: usize, height: usize) -> Vec<String> {
+    (0..height)
+        .map(|y| {
+            pixels[y * width..(y + 1) * width]
+                .iter()
+                .map(|&color| {
+                    let i = (brightness(color) * ASCII_RAMP.len() as f32) as usize;
+                    ASCII_RAMP[i.min(ASCII_RAMP.len() - 1)] as char
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn encode_ascii(pixels: &[Color], width: usize, height: usize) -> anyhow::Result<Vec<u8>> {
+    place_lines(ascii_lines(pixels, width, height))
+}
+
+// Dots are set where the brightness beats an ordered dither threshold, so
+// gradients come out as varying dot density
+pub fn braille_lines(pixels: &[Color], width: usize, height: usize) -> Vec<String> {
+    (0..height.div_ceil(4))
+        .map(|row| {
+            (0..width.div_ceil(2))
+                .map(|col| {
+                    let mut bits = 0;
+                    for (dy, dots) in BRAILLE_DOTS.iter().enumerate() {
+                        for (dx, dot) in dots.iter().enumerate() {
+                            let (x, y) = (col * 2 + dx, row * 4 + dy);
+                            if x >= width || y >= height {
+                                continue;
+                            }
+                            let threshold = (BAYER4[y % 4][x % 4] as f32 + 0.5) / 16.0;
+                            if brightness(pixels[y * width + x]) > threshold {
+                                bits |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn encode_braille(pixels: &[Color], width: usize, height: usize) -> anyhow::Result<Vec<u8>> {
+    place_lines(braille_lines(pixels, width, height))
+}
+
+fn place_lines(lines: Vec<String>) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    for (y, line) in lines.into_iter().enumerate() {
+        queue!(out, MoveTo(0, y as u16), Print(line))?;
+    }
+    Ok(out)
+}
+
+// Palette register of a pixel, dithered with the same pattern as the character output
+fn sixel_index(color: Color, x: usize, y: usize) -> usize {
+    let c = color.clamp(0.0, 1.0);
+    let dither = (BAYER4[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5;
+    let level = |v: f32, levels: usize| ((v * (levels - 1) as f32 + dither).round() as usize).min(levels - 1);
+    let [nr, ng, nb] = SIXEL_LEVELS;
+    (level(c.r, nr) * ng + level(c.g, ng)) * nb + level(c.b, nb)
+}
+
+fn push_sixels(out: &mut String, sixel: char, count: usize) {
+    if count > 3 {
+        let _ = write!(out, "!{count}{sixel}");
+    } else {
+        out.extend(std::iter::repeat_n(sixel, count));
+    }
+}
+
+// Every band of six rows is sent once per colour it contains, each pass sets the
+// pixels of that colour and returns to the start of the band
+pub fn encode_sixel(pixels: &[Color], width: usize, height: usize) -> anyhow::Result<Vec<u8>> {
+    let [nr, ng, nb] = SIXEL_LEVELS;
+    let mut sixel = format!("\x1bPq\"1;1;{width};{height}");
+    for i in 0..nr * ng * nb {
+        let (r, g, b) = (i / (ng * nb), i / nb % ng, i % nb);
+        let _ = write!(sixel, "#{i};2;{};{};{}", r * 100 / (nr - 1), g * 100 / (ng - 1), b * 100 / (nb - 1));
+    }
+    // Sixel bits of each colour in the current band, only allocated for colours in use
+    let mut bands: Vec<Vec<u8>> = vec![Vec::new(); nr * ng * nb];
+    let mut used = Vec::new();
+    for band in 0..height.div_ceil(6) {
+        for y in band * 6..(band * 6 + 6).min(height) {
+            for x in 0..width {
+                let i = sixel_index(pixels[y * width + x], x, y);
+                if bands[i].is_empty() {
+                    bands[i].resize(width, 0);
+                    used.push(i);
+                }
+                bands[i][x] |= 1 << (y - band * 6);
+            }
+        }
+        for (n, &i) in used.iter().enumerate() {
+            if n > 0 {
+                sixel.push('$');
+            }
+            let _ = write!(sixel, "#{i}");
+            let mut run = (0, 0);
+            for &bits in &bands[i] {
+                if bits != run.0 && run.1 > 0 {
+                    push_sixels(&mut sixel, (63 + run.0) as char, run.1);
+                    run.1 = 0;
+                }
+                run = (bits, run.1 + 1);
+            }
+            push_sixels(&mut sixel, (63 + run.0) as char, run.1);
+            bands[i].clear();
+        }
+        used.clear();
+        sixel.push('-');
+    }
+    sixel.push_str("\x1b\\");
+    let mut out = Vec::new();
+    queue!(out, MoveTo(0, 0), Print(sixel))?;
+    Ok(out)
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// Raw RGB sent in chunks, reusing the same image id replaces the previous frame.
+// The image is scaled to cover `cells` and responses are suppressed so they
+// don't show up as key presses.
+pub fn encode_kitty(pixels: &[Color], width: usize, height: usize, (cols, rows): (usize, usize)) -> anyhow::Result<Vec<u8>> {
+    const CHUNK: usize = 4096;
+    let rgb: Vec<u8> = pixels
+        .iter()
+        .flat_map(|color| {
+            let c = color.as_color32();
+            [c.r, c.g, c.b]
+        })
+        .collect();
+    let data = base64(&rgb);
+    let mut kitty = String::with_capacity(data.len() + data.len() / CHUNK * 16 + 64);
+    let chunks = data.as_bytes().chunks(CHUNK).collect::<Vec<_>>();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        if i == 0 {
+            let _ = write!(kitty, "\x1b_Ga=T,f=24,s={width},v={height},c={cols},r={rows},i=1,q=2,C=1,m={more};");
+        } else {
+            let _ = write!(kitty, "\x1b_Gm={more};");
+        }
+        kitty.push_str(std::str::from_utf8(chunk)?);
+        kitty.push_str("\x1b\\");
+    }
+    let mut out = Vec::new();
+    queue!(out, MoveTo(0, 0), Print(kitty))?;
+    Ok(out)
+}
+
+// Removes every kitty image from the screen
+pub const KITTY_CLEAR: &str = "\x1b_Ga=d,q=2\x1b\\";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_redundant_escapes() {
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let blue = Color::new(0.0, 0.0, 1.0, 1.0);
+        let pixels = [red, red, red, blue, blue, blue];
+        let out = encode_half_blocks(&pixels, 3, 2, ColorDepth::TrueColor).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "\x1b[1;1H\x1b[48;2;0;0;255m\x1b[38;2;255;0;0m▀▀▀\x1b[0m");
+    }
+
+    #[test]
+    fn palettes() {
+        assert_eq!(nearest_256([255.0, 0.0, 0.0]), 196);
+        assert_eq!(nearest_256([128.0, 128.0, 128.0]), 244);
+        assert_eq!(nearest_16([250.0, 250.0, 250.0]), 15);
+        assert_eq!(nearest_16([200.0, 10.0, 0.0]), 1);
+
+        let mut sgr = String::new();
+        CellColor::Index(9).sgr(ColorDepth::Ansi16, true, &mut sgr);
+        CellColor::Index(2).sgr(ColorDepth::Ansi16, false, &mut sgr);
+        assert_eq!(sgr, "\x1b[101m\x1b[32m");
+    }
+
+    #[test]
+    fn render_sizes() {
+        assert_eq!(Output::HalfBlock.render_size((80, 24), (8, 16)), (80, 48));
+        assert_eq!(Output::Braille.render_size((80, 24), (8, 16)), (160, 96));
+        assert_eq!(Output::Kitty.render_size((80, 24), (8, 16)), (640, 384));
+        assert_eq!(Output::Sixel.render_size((80, 24), (9, 17)), (720, 390));
+    }
+
+    #[test]
+    fn ascii_ramp() {
+        let pixels = [Color::BLACK, Color::new_value(0.5, 1.0), Color::WHITE];
+        let out = String::from_utf8(encode_ascii(&pixels, 3, 1).unwrap()).unwrap();
+        assert_eq!(out, "\x1b[1;1H +@");
+    }
+
+    #[test]
+    fn braille_dots() {
+        // Left column lit, right column dark
+        let pixels: Vec<Color> = (0..8).map(|i| if i % 2 == 0 { Color::WHITE } else { Color::BLACK }).collect();
+        let out = String::from_utf8(encode_braille(&pixels, 2, 4).unwrap()).unwrap();
+        assert_eq!(out, "\x1b[1;1H\u{2847}");
+    }
+
+    #[test]
+    fn sixel_bands() {
+        // One red column over a black background, 2x7 pixels make two bands
+        let red = Color::new(1.0, 0.0, 0.0, 1.0);
+        let pixels: Vec<Color> = (0..14).map(|i| if i % 2 == 0 { red } else { Color::BLACK }).collect();
+        let out = String::from_utf8(encode_sixel(&pixels, 2, 7).unwrap()).unwrap();
+        let red = sixel_index(red, 0, 0);
+        let body = out.split_once("#251;2;100;100;100").unwrap().1;
+        assert_eq!(body, format!("#{red}~?$#0?~-#{red}@?$#0?@-\x1b\\"));
+    }
+
+    #[test]
+    fn kitty_chunks() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+
+        let pixels = vec![Color::WHITE; 64 * 32];
+        let out = String::from_utf8(encode_kitty(&pixels, 64, 32, (8, 2)).unwrap()).unwrap();
+        assert!(out.starts_with("\x1b[1;1H\x1b_Ga=T,f=24,s=64,v=32,c=8,r=2,i=1,q=2,C=1,m=1;/////"));
+        assert_eq!(out.matches("\x1b_G").count(), 2);
+        assert!(out.contains("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn parse_capabilities() {
+        let caps = Capabilities::parse("\x1b_Gi=31;OK\x1b\\\x1b[4;720;1280t\x1b[?62;4;22c");
+        assert_eq!(caps, Capabilities { kitty: true, sixel: true, pixels: Some((1280, 720)) });
+        assert_eq!(caps.preferred_output(), Output::Kitty);
+        assert_eq!(caps.cell_pixels((160, 45)), (8, 16));
+
+        let caps = Capabilities::parse("\x1b[?1;2c");
+        assert_eq!(caps, Capabilities::default());
+        assert_eq!(caps.cell_pixels((80, 24)), DEFAULT_CELL_PIXELS);
+        assert!(Capabilities::answered(b"\x1b[4;1;2t\x1b[?64;4c"));
+        assert!(!Capabilities::answered(b"\x1b[4;1;2t"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reads_answer_only() {
+        use std::{fs::File, io::Read, os::fd::FromRawFd, time::Instant};
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (mut reader, mut writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+        // Nothing arrives, the read gives up at the deadline
+        let start = Instant::now();
+        assert!(Capabilities::read_answer(fds[0], Duration::from_millis(50)).is_empty());
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // A key pressed after the answer stays in the pipe
+        writer.write_all(b"\x1b[?62;4cq").unwrap();
+        assert_eq!(Capabilities::read_answer(fds[0], Duration::from_secs(1)), b"\x1b[?62;4c");
+        drop(writer);
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "q");
+    }
+
+    #[test]
+    fn dithering_mixes_colours() {
+        // Mid grey has no exact match in 16 colours, dithering alternates between neighbours
+        let grey = Color::new_value(0.35, 1.0);
+        let cells: Vec<CellColor> = (0..4).map(|x| quantize(grey, x, 0, ColorDepth::Ansi16)).collect();
+        assert!(cells.iter().any(|&c| c != cells[0]));
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use interlumen_core::Color;
+use interlumen_render::{ConvergenceStats, RenderProgress, RendererSettings};
+
+use crate::engine::Engine;
+
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+    pub progress: RenderProgress,
+    pub stats: Option<ConvergenceStats>,
+    // Accumulated samples per pixel
+    pub samples: usize,
+    pub rays_traced: u64,
+    // Settings the frame was rendered with
+    pub settings: Option<RendererSettings>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+            progress: RenderProgress::default(),
+            stats: None,
+            samples: 0,
+            rays_traced: 0,
+            settings: None,
+        }
+    }
+}
+
+// Renders on its own thread and keeps only the latest finished frame,
+// the display loop picks it up whenever it's ready to present
+pub struct RenderWorker {
+    frame: Arc<Mutex<Option<Frame>>>,
+    size: Arc<Mutex<(usize, usize)>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RenderWorker {
+    // `render` is called in a loop with the current output size and returns the next
+    // frame to publish. It should return regularly and give up early once `cancel` is set.
+    pub fn spawn<F>(engine: Arc<RwLock<Engine>>, width: usize, height: usize, mut render: F) -> Self
+    where
+        F: FnMut(&RwLock<Engine>, usize, usize, &AtomicBool) -> Option<Frame> + Send + 'static,
+    {
+        let frame = Arc::new(Mutex::new(None));
+        let size = Arc::new(Mutex::new((width, height)));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (frame, size, stop) = (frame.clone(), size.clone(), stop.clone());
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let (width, height) = *size.lock().unwrap();
+                    if let Some(new_frame) = render(&engine, width, height, &stop) {
+                        *frame.lock().unwrap() = Some(new_frame);
+                    }
+                    // Give the display thread a chance to take the engine lock
+                    thread::sleep(Duration::from_millis(1));
+                }
+            })
+        };
+        Self {
+            frame,
+            size,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn resize(&self, width: usize, height: usize) {
+        *self.size.lock().unwrap() = (width, height);
+    }
+
+    pub fn take_frame(&self) -> Option<Frame> {
+        self.frame.lock().unwrap().take()
+    }
+}
+
+impl Drop for RenderWorker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn publishes_latest_frame() {
+        let engine = Arc::new(RwLock::new(Engine::new()));
+        let worker = RenderWorker::spawn(engine, 4, 2, |engine, width, height, _cancel| {
+            let eng = engine.read().unwrap();
+            Some(Frame::new(width, height, eng.renderer_driver.draw_image(width, height, 0)))
+        });
+        worker.resize(2, 2);
+        let start = Instant::now();
+        let frame = loop {
+            if let Some(frame) = worker.take_frame() {
+                if frame.width == 2 {
+                    break frame;
+                }
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "no frame after resizing");
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(frame.pixels.len(), 4);
+    }
+}